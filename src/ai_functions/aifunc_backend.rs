@@ -6,8 +6,7 @@ pub fn print_backend_webserver_code(_project_description_and_template: &str) {
     /// IMPORTANT: The backend code is ONLY an example. If the Project Description requires it, make as many changes as you like.
     /// IMPORTANT: You do not need to follow the backend code exactly. Write functions that make sense for the users request if required.
     /// FUNCTION: Takes an existing set of code marked as CODE_TEMPLATE and updates or re-writes it to work for the purpose in the PROJECT_DESCRIPTION
-    /// IMPORTANT: The following libraries are already installed
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
+    /// IMPORTANT: The code is written for the FRAMEWORK given and only the INSTALLED_LIBRARIES are available
     /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
     /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
     println!(OUTPUT)
@@ -20,8 +19,59 @@ pub fn print_improved_webserver_code(_project_description_and_template: &str) {
     ///   1. Removes any bugs in the code and adds minor additional functionality
     ///   2. Makes sure everything requested in the spec from a backend standpoint was followed. If not, add the feature. No code should be implemented later. Everything should be written now.
     ///   3. ONLY writes the code. No commentary.
-    /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
-    ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+    /// IMPORTANT: Does not use ANY libraries other than the INSTALLED_LIBRARIES and keeps to the FRAMEWORK given
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_revised_webserver_code(_code_and_change_request: &str) {
+    /// INPUT: Takes in the EXISTING_CODE of a working backend, the FRAMEWORK and INSTALLED_LIBRARIES, the PROJECT_DESCRIPTION and a CHANGE_REQUEST from the user
+    /// FUNCTION: Makes the changes asked for in the CHANGE_REQUEST as an incremental edit of the EXISTING_CODE
+    /// IMPORTANT: Keeps every existing route and feature working unless the CHANGE_REQUEST asks for it to be removed
+    /// IMPORTANT: Does not use ANY libraries other than the INSTALLED_LIBRARIES
+    /// OUTPUT: Print ONLY the complete updated code, nothing else. This function ONLY prints code.
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_improved_webserver_edits(_code_and_project_description: &str) {
+    /// INPUT: Takes in the EXISTING_CODE of a backend, the FRAMEWORK and INSTALLED_LIBRARIES and the PROJECT_DESCRIPTION for a website backend build
+    /// FUNCTION: Performs the following tasks as edits to the EXISTING_CODE:
+    ///   1. Removes any bugs in the code and adds minor additional functionality
+    ///   2. Makes sure everything requested in the spec from a backend standpoint was followed. If not, add the feature. No code should be implemented later. Everything should be written now.
+    /// IMPORTANT: Does not use ANY libraries other than the INSTALLED_LIBRARIES
+    /// OUTPUT: Prints ONLY search/replace edit blocks, nothing else. Never reprints the whole file. Each block is:
+    /// <<<<<<< SEARCH
+    /// exact lines copied from EXISTING_CODE, enough of them to match only one place
+    /// =======
+    /// the lines which replace them
+    /// >>>>>>> REPLACE
+    /// To add new code, SEARCH for the lines next to where it goes and repeat them in the replacement together with the new code.
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_fixed_code_edits(_broken_code_with_bugs: &str) {
+    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found
+    /// FUNCTION: Removes bugs from code by editing only the lines that cause the ERROR_BUGS
+    /// OUTPUT: Prints ONLY search/replace edit blocks, nothing else. Never reprints the whole file. Each block is:
+    /// <<<<<<< SEARCH
+    /// exact lines copied from BROKEN_CODE, enough of them to match only one place
+    /// =======
+    /// the fixed lines which replace them
+    /// >>>>>>> REPLACE
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_fixed_code_with_tools(_errors_and_libraries: &str) {
+    /// INPUT: Takes in the ERROR_BUGS found in the Rust web server project whose code is in src/main.rs, and the INSTALLED_LIBRARIES
+    /// FUNCTION: Fixes the bugs by working on the project files with the tools:
+    ///   1. Reads src/main.rs and searches the code to find the cause of each error
+    ///   2. Writes the complete fixed src/main.rs with write_file
+    ///   3. Runs cargo_check and repeats from step 1 until it succeeds
+    /// IMPORTANT: Does not use ANY libraries other than the INSTALLED_LIBRARIES
+    /// OUTPUT: Prints ONLY a one line summary of what was fixed, the code itself stays in the files
     println!(OUTPUT)
 }
 
@@ -33,13 +83,29 @@ pub fn print_fixed_code(_broken_code_with_bugs: &str) {
     println!(OUTPUT)
 }
 
+#[ai_function]
+pub fn print_backend_integration_tests(_code_and_endpoints: &str) {
+    /// INPUT: Takes in Rust webserver CODE_INPUT written with the FRAMEWORK, the INSTALLED_LIBRARIES and the API_ENDPOINTS JSON schema extracted from that code
+    /// FUNCTION: Writes a Rust integration test file which will be saved as tests/api_tests.rs inside the web server crate
+    /// LOGIC: Writes at least one test for every route in API_ENDPOINTS:
+    ///   1. The crate is a binary so the server code is imported with: #[path = "../src/main.rs"] mod app;
+    ///   2. For actix-web, builds the App with the same app_data and routes as the server using actix_web::test::init_service inside #[actix_web::test] tests
+    ///   3. For other frameworks, serves the same routes on 127.0.0.1:0 inside #[tokio::test] tests and calls them over a tokio TcpStream or an installed http client
+    ///   4. Sends requests using the "method", "route" and "request_body" of each endpoint
+    ///   5. Dynamic routes such as /item/{id} are called with a value created earlier in the same test
+    ///   6. Asserts the response status and, where the "response" is known, the JSON shape of the body
+    /// IMPORTANT: Tests must not call external urls. Only the INSTALLED_LIBRARIES are available
+    /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
+    println!(OUTPUT)
+}
+
 #[ai_function]
 pub fn print_rest_api_endpoints(_code_input: &str) {
-    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web
+    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web or axum
     /// FUNCTION: Prints out the JSON schema for url endpoints and their respective types
     /// LOGIC: Script analyses all code and can categorize into the following object keys:
     ///   "route": This represents the url path of the endpoint
-    ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id} as an example, then this will be set to true
+    ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id}, or a colon segment such as :id, then this will be set to true
     ///   "method": This represents the method being called
     ///   "request_body": This represents the body of a post method request
     ///   "response": This represents the output based upon the structs in the code and understanding the functions
@@ -13,3 +13,20 @@ pub fn convert_user_input_to_goal(_user_request: &str) {
     ///   OUTPUT = "build a website that fetches and stores crypto price data within a supabase setup including a frontend UI to fetch the data."
     println!(OUTPUT)
 }
+
+#[ai_function]
+pub fn print_revision_route(_change_request_and_project: &str) {
+    /// Input: Takes in a CHANGE_REQUEST from the user and the PROJECT_DESCRIPTION of a website which has already been built
+    /// Function: Decides which agent should handle the change request
+    /// Logic:
+    ///   "architect" if the change alters what the website is for, needs new external data sources or changes login or CRUD requirements
+    ///   "backend" if the change can be made by editing the existing backend code, such as new routes, fields, validation or bug fixes
+    /// Output: Prints ONLY one word, either architect or backend
+    /// Example 1:
+    ///   change_request = "also show the price of ethereum from a public api"
+    ///   OUTPUT = architect
+    /// Example 2:
+    ///   change_request = "add a completed flag to each todo item"
+    ///   OUTPUT = backend
+    println!(OUTPUT)
+}
@@ -0,0 +1,13 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_react_step(_objective_and_project: &str) {
+    /// INPUT: Takes in the POSITION and OBJECTIVE of an agent and the PROJECT it works on as a JSON fact sheet
+    /// FUNCTION: Works towards the OBJECTIVE one step at a time:
+    ///   1. Think: decides what the next step towards the OBJECTIVE is
+    ///   2. Act: calls the tools needed for that step
+    ///   3. Observe: reads the tool results, then goes back to 1
+    /// IMPORTANT: Only uses the tools it has been given. Calls the finish tool with the final result once the OBJECTIVE is met, or with the reason when it cannot be met
+    /// OUTPUT: Prints ONLY one short sentence with the thought of the current step, together with its tool calls
+    println!(OUTPUT)
+}
@@ -0,0 +1,29 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_code_review(_code_and_spec: &str) {
+    /// INPUT: Takes in Rust webserver BACKEND_CODE, the PROJECT_DESCRIPTION, the allowed EXTERNAL_URLS and the API_ENDPOINTS if known
+    /// FUNCTION: Reviews the BACKEND_CODE before it is built and run on the user's machine and lists every problem found
+    /// LOGIC: Checks the code for the following categories:
+    ///   "security": runs commands or processes, reads or writes files outside the project directory, calls urls which are not in EXTERNAL_URLS, contains hardcoded secrets, passwords or api keys
+    ///   "correctness": code that will not compile, panics on user input, broken or missing error handling, routes that do not do what they claim
+    ///   "spec_coverage": features requested in the PROJECT_DESCRIPTION which are missing from the code
+    /// SEVERITY: "high" for anything which is unsafe to run or makes the project unusable, "medium" for bugs and missing features, "low" for style and minor issues
+    /// IMPORTANT: Only prints out the JSON list. No commentary or anything else. Prints [] if nothing was found.
+    /// OUTPUT: Prints a list of findings in the following format:
+    /// [
+    ///   {
+    ///     "severity": "high",
+    ///     "category": "security",
+    ///     "description": "main spawns std::process::Command to run 'rm -rf' on a user supplied path",
+    ///     "line": 42
+    ///   },
+    ///   {
+    ///     "severity": "medium",
+    ///     "category": "spec_coverage",
+    ///     "description": "the project description asks for user logout but no logout route exists",
+    ///     "line": null
+    ///   }
+    /// ]
+    println!(OUTPUT)
+}
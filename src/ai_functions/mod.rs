@@ -1,3 +1,33 @@
 pub mod aifunc_architect;
 pub mod aifunc_backend;
 pub mod aifunc_managing;
+pub mod aifunc_react;
+pub mod aifunc_reviewer;
+
+pub type AiFunction = fn(&str) -> &'static str;
+
+//every ai_function by name, for running them outside of the agents
+pub const AI_FUNCTIONS: [(&str, AiFunction); 15] = [
+    ("print_project_scope", aifunc_architect::print_project_scope),
+    ("print_site_urls", aifunc_architect::print_site_urls),
+    ("print_backend_webserver_code", aifunc_backend::print_backend_webserver_code),
+    ("print_improved_webserver_code", aifunc_backend::print_improved_webserver_code),
+    ("print_revised_webserver_code", aifunc_backend::print_revised_webserver_code),
+    ("print_improved_webserver_edits", aifunc_backend::print_improved_webserver_edits),
+    ("print_fixed_code_edits", aifunc_backend::print_fixed_code_edits),
+    ("print_fixed_code_with_tools", aifunc_backend::print_fixed_code_with_tools),
+    ("print_fixed_code", aifunc_backend::print_fixed_code),
+    ("print_backend_integration_tests", aifunc_backend::print_backend_integration_tests),
+    ("print_rest_api_endpoints", aifunc_backend::print_rest_api_endpoints),
+    ("convert_user_input_to_goal", aifunc_managing::convert_user_input_to_goal),
+    ("print_revision_route", aifunc_managing::print_revision_route),
+    ("print_code_review", aifunc_reviewer::print_code_review),
+    ("print_react_step", aifunc_react::print_react_step),
+];
+
+pub fn find_ai_function(name: &str) -> Option<AiFunction> {
+    AI_FUNCTIONS
+        .iter()
+        .find(|(function_name, _)| *function_name == name)
+        .map(|(_, ai_func)| *ai_func)
+}
@@ -1,13 +1,98 @@
-use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
+use crate::apis::llm_cache::{cache_key, is_cache_enabled, record_cache_lookup, CachedCompletion, LlmCache};
+use crate::apis::rate_limiter::{estimate_tokens, RateLimiter, RatePermit, RATE_LIMITER};
+use crate::apis::retry::{parse_retry_after, RetryPolicy};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::models::general::llm::{
+    APIStreamResponse, APIToolCallDelta, APIUsage, ChatCompletion, Message, StreamOptions, Tool, ToolCall,
+};
 use dotenv::dotenv;
-use reqwest::{Client, Url};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode, Url};
 use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::signal;
+use tokio::sync::Notify;
+use tokio::time;
 
 use reqwest::header::{HeaderMap, HeaderValue};
 
-//call large language model
+//far more parallel tool calls than a model makes in one reply
+const MAX_STREAMED_TOOL_CALLS: usize = 64;
+
+//what the model answered, text or tool calls or both
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssistantReply {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl AssistantReply {
+    //the assistant message to send back along with the tool results
+    pub fn to_message(&self) -> Message {
+        Message {
+            role: "assistant".to_string(),
+            content: self.content.clone(),
+            tool_calls: (!self.tool_calls.is_empty()).then(|| self.tool_calls.clone()),
+            tool_call_id: None,
+        }
+    }
+}
+
+//call large language model, streaming the completion as it is generated
+
+pub async fn call_gpt(
+    agent: &str,
+    messages: Vec<Message>,
+    is_cacheable: bool,
+    on_progress: &mut (dyn FnMut(&StreamProgress) + Send),
+) -> Result<String, Box<dyn std::error::Error + Send>> {
+    call_gpt_with_tools(agent, messages, &[], is_cacheable, on_progress)
+        .await
+        .map(|reply| reply.content)
+}
+
+//the model may answer with tool calls instead of text when tools are offered
+pub async fn call_gpt_with_tools(
+    agent: &str,
+    messages: Vec<Message>,
+    tools: &[Tool],
+    is_cacheable: bool,
+    on_progress: &mut (dyn FnMut(&StreamProgress) + Send),
+) -> Result<AssistantReply, Box<dyn std::error::Error + Send>> {
+    //create chat completion
+
+    let chat_completion = ChatCompletion {
+        model: "gpt-3.5-turbo".to_string(),
+        messages,
+        temperature: 0.1,
+        stream: true,
+        stream_options: Some(StreamOptions { include_usage: true }),
+        tools: (!tools.is_empty()).then(|| tools.to_vec()),
+    };
+
+    //identical requests are answered from disk
+    let cache: Option<LlmCache> = (is_cacheable && is_cache_enabled()).then(LlmCache::from_env);
+    let cache_key: String = cache_key(&chat_completion);
+
+    if let Some(cache) = &cache {
+        let cached: Option<CachedCompletion> = cache.get(&cache_key);
+        record_cache_lookup(cached.is_some());
+        emit(RunEvent::LlmCache {
+            key: cache_key.clone(),
+            hit: cached.is_some(),
+        });
+
+        if let Some(cached) = cached {
+            return Ok(AssistantReply {
+                content: cached.content,
+                tool_calls: cached.tool_calls,
+            });
+        }
+    }
 
-pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
     dotenv().ok();
 
     let api_key: String = env::var("OPEN_AI_KEY").unwrap();
@@ -39,17 +124,10 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
 
     let client = Client::builder()
         .default_headers(headers)
+        .connect_timeout(Duration::from_secs(10))
         .build()
         .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
 
-    //create chat completion
-
-    let chat_completion = ChatCompletion {
-        model: "gpt-3.5-turbo".to_string(),
-        messages,
-        temperature: 0.1,
-    };
-
     // //troubleshooting
 
     // let res_raw = client
@@ -63,29 +141,343 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
 
     //Extract API Response
 
-    let res: APIResponse = client
-        .post(url)
-        .json(&chat_completion)
-        .send()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
-        .json()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+    //the org key is shared, so every call queues for its share of the rate limits
+    let estimated_tokens: u32 = chat_completion
+        .messages
+        .iter()
+        .map(|message| estimate_tokens(&message.content))
+        .sum();
+    let rate_request: RateRequest = RateRequest {
+        limiter: &RATE_LIMITER,
+        agent,
+        estimated_tokens,
+    };
+
+    let completion: StreamedCompletion = request_completion(
+        &client,
+        url,
+        &chat_completion,
+        &RetryPolicy::from_env(),
+        Some(&rate_request),
+        on_progress,
+    )
+    .await
+    .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+    let usage: Option<APIUsage> = completion.usage;
+    let reply: AssistantReply = AssistantReply {
+        content: completion.content,
+        tool_calls: completion.tool_calls,
+    };
+
+    if let Some(usage) = &usage {
+        emit(RunEvent::TokenUsage {
+            model: chat_completion.model.clone(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        });
+    }
 
-    // print!("hiii   {:?}",res);
+    if let Some(cache) = &cache {
+        if let Err(e) = cache.put(&cache_key, &reply.content, &reply.tool_calls, &chat_completion.model) {
+            tracing::warn!(error = %e, "failed to cache LLM response");
+        }
+    }
 
     //send response
 
-    // Ok(res.choices[0].message.content.clone())
-    Ok(res.choices[0].message.content.clone())
+    Ok(reply)
+}
+
+#[derive(Debug, Default)]
+pub struct StreamedCompletion {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Option<APIUsage>,
+}
+
+//who is asking and how big the request is, so every attempt queues for the rate limits
+pub struct RateRequest<'a> {
+    pub limiter: &'a RateLimiter,
+    pub agent: &'a str,
+    pub estimated_tokens: u32,
+}
+
+//retries the completion on rate limits, server errors, timeouts and dropped connections
+pub async fn request_completion(
+    client: &Client,
+    url: &str,
+    chat_completion: &ChatCompletion,
+    policy: &RetryPolicy,
+    rate_request: Option<&RateRequest<'_>>,
+    on_progress: &mut (dyn FnMut(&StreamProgress) + Send),
+) -> Result<StreamedCompletion, LlmError> {
+    let mut attempt: u32 = 0;
+
+    loop {
+        let permit: Option<RatePermit> = match rate_request {
+            Some(rate_request) => Some(rate_request.limiter.acquire(rate_request.agent, rate_request.estimated_tokens).await),
+            None => None,
+        };
+
+        let completion = send_completion(client, url, chat_completion, policy.request_timeout, on_progress).await;
+        if let (Some(permit), Ok(StreamedCompletion { usage: Some(usage), .. })) = (&permit, &completion) {
+            permit.record_tokens(usage.total_tokens);
+        }
+        //the permit is given back before the backoff so other calls can go meanwhile
+        drop(permit);
+
+        match completion {
+            Ok(completion) => return Ok(completion),
+            Err(e) if e.is_retryable() && attempt < policy.max_retries => {
+                let delay: Duration = policy.retry_delay(attempt, &e);
+                attempt += 1;
+
+                tracing::warn!(attempt, error = %e, delay_ms = delay.as_millis() as u64, "retrying LLM call");
+                emit(RunEvent::LlmRetry {
+                    attempt,
+                    error: e.to_string(),
+                    delay_ms: delay.as_millis() as u64,
+                });
+
+                time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn send_completion(
+    client: &Client,
+    url: &str,
+    chat_completion: &ChatCompletion,
+    request_timeout: Duration,
+    on_progress: &mut (dyn FnMut(&StreamProgress) + Send),
+) -> Result<StreamedCompletion, LlmError> {
+    let response: Response = time::timeout(request_timeout, client.post(url).json(chat_completion).send())
+        .await
+        .map_err(|_| LlmError::Timeout)??;
+
+    let status: StatusCode = response.status();
+    if !status.is_success() {
+        let retry_after: Option<Duration> = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body: String = response.text().await.unwrap_or_default();
+
+        return Err(LlmError::Status {
+            status: status.as_u16(),
+            retry_after,
+            body,
+        });
+    }
+
+    read_completion_stream(response, request_timeout, on_progress).await
+}
+
+#[derive(Debug)]
+pub enum LlmError {
+    Cancelled,
+    Timeout,
+    Connection(String),
+    Status {
+        status: u16,
+        retry_after: Option<Duration>,
+        body: String,
+    },
+    Decode(String),
+}
+
+impl LlmError {
+    //bad requests and bad keys fail the same way however often they are sent
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Timeout | LlmError::Connection(_) => true,
+            LlmError::Status { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+            LlmError::Cancelled | LlmError::Decode(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LlmError::Cancelled => write!(f, "LLM call cancelled by user"),
+            LlmError::Timeout => write!(f, "LLM call timed out"),
+            LlmError::Connection(e) => write!(f, "LLM connection failed: {}", e),
+            LlmError::Status { status, body, .. } => write!(f, "LLM call failed with status {}: {}", status, body),
+            LlmError::Decode(e) => write!(f, "failed to decode LLM response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            LlmError::Timeout
+        } else if e.is_decode() {
+            LlmError::Decode(e.to_string())
+        } else {
+            LlmError::Connection(e.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamProgress {
+    pub tokens_received: usize,
+    pub elapsed: Duration,
+    pub latest_text: String,
+}
+
+static ACTIVE_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+static CANCEL_STREAMS: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+//Ctrl-C cancels the completions being streamed, or quits when nothing is streaming
+pub fn handle_ctrl_c() {
+    tokio::spawn(async {
+        while signal::ctrl_c().await.is_ok() {
+            if ACTIVE_STREAMS.load(Ordering::SeqCst) == 0 {
+                std::process::exit(130);
+            }
+            CANCEL_STREAMS.notify_waiters();
+        }
+    });
+}
+
+struct ActiveStream;
+
+impl ActiveStream {
+    fn start() -> Self {
+        ACTIVE_STREAMS.fetch_add(1, Ordering::SeqCst);
+        ActiveStream
+    }
+}
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        ACTIVE_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+//collects the data lines of server-sent events which may arrive split across chunks
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    //buffers bytes rather than text so characters split across chunks survive
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut payloads: Vec<String> = vec![];
+        while let Some(line_end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line_bytes: Vec<u8> = self.buffer.drain(..=line_end).collect();
+            let line: String = String::from_utf8_lossy(&line_bytes).trim_end().to_string();
+
+            if let Some(data) = line.strip_prefix("data:") {
+                payloads.push(data.trim_start().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+//tool calls arrive in pieces, the id and name first and the arguments spread over later deltas
+pub fn merge_tool_call_deltas(tool_calls: &mut Vec<ToolCall>, deltas: Vec<APIToolCallDelta>) {
+    for delta in deltas {
+        //the index comes from the stream, a bogus one must not allocate a huge list
+        if delta.index >= MAX_STREAMED_TOOL_CALLS {
+            tracing::warn!(index = delta.index, "dropping tool call delta with an out of range index");
+            continue;
+        }
+        if tool_calls.len() <= delta.index {
+            tool_calls.resize(delta.index + 1, ToolCall::default());
+        }
+        let tool_call: &mut ToolCall = &mut tool_calls[delta.index];
+        tool_call.call_type = "function".to_string();
+
+        if let Some(id) = delta.id {
+            tool_call.id = id;
+        }
+        if let Some(function) = delta.function {
+            tool_call.function.name.push_str(function.name.as_deref().unwrap_or_default());
+            tool_call.function.arguments.push_str(function.arguments.as_deref().unwrap_or_default());
+        }
+    }
+}
+
+async fn read_completion_stream(
+    mut response: Response,
+    idle_timeout: Duration,
+    on_progress: &mut (dyn FnMut(&StreamProgress) + Send),
+) -> Result<StreamedCompletion, LlmError> {
+    let _active_stream: ActiveStream = ActiveStream::start();
+    let started: Instant = Instant::now();
+
+    let mut parser: SseParser = SseParser::default();
+    let mut completion: StreamedCompletion = StreamedCompletion::default();
+    let mut tokens_received: usize = 0;
+
+    loop {
+        let chunk = tokio::select! {
+            _ = CANCEL_STREAMS.notified() => return Err(LlmError::Cancelled),
+            chunk = time::timeout(idle_timeout, response.chunk()) => chunk.map_err(|_| LlmError::Timeout)??,
+        };
+
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        for payload in parser.push(&chunk) {
+            if payload == "[DONE]" {
+                continue;
+            }
+
+            let event: APIStreamResponse =
+                serde_json::from_str(&payload).map_err(|e| LlmError::Decode(e.to_string()))?;
+
+            if event.usage.is_some() {
+                completion.usage = event.usage;
+            }
+
+            for choice in event.choices {
+                if let Some(deltas) = choice.delta.tool_calls {
+                    merge_tool_call_deltas(&mut completion.tool_calls, deltas);
+                }
+                if let Some(delta) = choice.delta.content {
+                    //every content delta is one token
+                    tokens_received += 1;
+                    completion.content.push_str(&delta);
+
+                    on_progress(&StreamProgress {
+                        tokens_received,
+                        elapsed: started.elapsed(),
+                        latest_text: delta,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(completion)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
     use std::vec;
 
     use super::*;
+    use crate::apis::rate_limiter::RateLimits;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
 
     #[tokio::test]
 
@@ -93,11 +485,12 @@ mod tests {
         let message = Message {
             role: "user".to_string(),
             content: "hi there, this is a test. give me a short answer.".to_string(),
+            ..Default::default()
         };
 
         let messages = vec![message];
 
-        let res = call_gpt(messages).await;
+        let res = call_gpt("test", messages, false, &mut |_| {}).await;
         if let Ok(res_str) = res {
             dbg!(res_str);
             assert!(true)
@@ -106,4 +499,194 @@ mod tests {
             assert!(false)
         }
     }
+
+    #[test]
+    fn tests_sse_parser_handles_split_chunks() {
+        let mut parser: SseParser = SseParser::default();
+
+        let first: Vec<String> = parser.push(b"data: {\"choices\":[{\"delta\":{\"content\":\"fn\"}}]}\r\n\r\ndata: {\"choi");
+        let second: Vec<String> = parser.push(b"ces\":[]}\n\n: keep-alive\n\ndata: [DONE]\n\n");
+
+        assert_eq!(first, vec![r#"{"choices":[{"delta":{"content":"fn"}}]}"#.to_string()]);
+        assert_eq!(second, vec![r#"{"choices":[]}"#.to_string(), "[DONE]".to_string()]);
+
+        let event: APIStreamResponse = serde_json::from_str(&first[0]).unwrap();
+        assert_eq!(event.choices[0].delta.content.as_deref(), Some("fn"));
+    }
+
+    //answers each connection with the next canned response, an empty one never answers
+    async fn start_stub_server(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url: String = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let requests: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        let counter: Arc<AtomicUsize> = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                read_stub_request(&mut socket).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                if response.is_empty() {
+                    tokio::spawn(async move {
+                        time::sleep(Duration::from_secs(5)).await;
+                        drop(socket);
+                    });
+                    continue;
+                }
+
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+
+        (url, requests)
+    }
+
+    async fn read_stub_request(socket: &mut TcpStream) {
+        let mut request: Vec<u8> = vec![];
+        let mut buffer: [u8; 4096] = [0; 4096];
+
+        loop {
+            let read: usize = socket.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+
+            let request_str: String = String::from_utf8_lossy(&request).to_string();
+            if let Some(header_end) = request_str.find("\r\n\r\n") {
+                let content_length: usize = request_str[..header_end]
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    return;
+                }
+            }
+            if read == 0 {
+                return;
+            }
+        }
+    }
+
+    fn stub_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            request_timeout: Duration::from_millis(300),
+        }
+    }
+
+    fn stub_completion() -> ChatCompletion {
+        ChatCompletion {
+            model: "gpt-3.5-turbo".to_string(),
+            messages: vec![],
+            temperature: 0.1,
+            stream: true,
+            stream_options: None,
+            tools: None,
+        }
+    }
+
+    const STREAMED_COMPLETION: &str = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n\
+data: {\"choices\":[{\"delta\":{\"content\":\"hello\"}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+data: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2,\"total_tokens\":7}}\n\n\
+data: [DONE]\n\n";
+
+    #[tokio::test]
+    async fn tests_retries_rate_limits_and_server_errors() {
+        let (url, requests) = start_stub_server(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            STREAMED_COMPLETION,
+        ])
+        .await;
+
+        let limiter: RateLimiter = RateLimiter::new(RateLimits::default());
+        let rate_request: RateRequest = RateRequest {
+            limiter: &limiter,
+            agent: "test",
+            estimated_tokens: 10,
+        };
+
+        let mut progress: Vec<usize> = vec![];
+        let completion: StreamedCompletion = request_completion(
+            &Client::new(),
+            &url,
+            &stub_completion(),
+            &stub_policy(),
+            Some(&rate_request),
+            &mut |p| progress.push(p.tokens_received),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(completion.content, "hello world");
+        assert_eq!(completion.usage.unwrap().total_tokens, 7);
+        assert_eq!(progress, vec![1, 2]);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        //every attempt counts against the requests per minute
+        assert_eq!(limiter.metrics().requests, 3);
+    }
+
+    #[tokio::test]
+    async fn tests_streamed_tool_calls_are_assembled() {
+        let (url, _) = start_stub_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n\
+data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"read_file\",\"arguments\":\"\"}}]}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"path\\\": \"}}]}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":1,\"id\":\"call_2\",\"function\":{\"name\":\"cargo_check\",\"arguments\":\"{}\"}}]}}]}\n\n\
+data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"src/main.rs\\\"}\"}}]}}]}\n\n\
+data: [DONE]\n\n",
+        ])
+        .await;
+
+        let completion: StreamedCompletion =
+            request_completion(&Client::new(), &url, &stub_completion(), &stub_policy(), None, &mut |_| {})
+                .await
+                .unwrap();
+
+        assert!(completion.content.is_empty());
+        assert_eq!(completion.tool_calls.len(), 2);
+        assert_eq!(completion.tool_calls[0].id, "call_1");
+        assert_eq!(completion.tool_calls[0].function.name, "read_file");
+        assert_eq!(completion.tool_calls[0].function.arguments, "{\"path\": \"src/main.rs\"}");
+        assert_eq!(completion.tool_calls[1].function.name, "cargo_check");
+
+        let mut tool_calls: Vec<ToolCall> = completion.tool_calls.clone();
+        let bogus: Vec<APIToolCallDelta> =
+            serde_json::from_str(r#"[{"index": 18446744073709551615, "function": {"name": "write_file"}}]"#).unwrap();
+        merge_tool_call_deltas(&mut tool_calls, bogus);
+        assert_eq!(tool_calls, completion.tool_calls);
+    }
+
+    #[tokio::test]
+    async fn tests_fatal_errors_are_not_retried() {
+        let (url, requests) = start_stub_server(vec![
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 11\r\nConnection: close\r\n\r\nbad api key",
+        ])
+        .await;
+
+        let res = request_completion(&Client::new(), &url, &stub_completion(), &stub_policy(), None, &mut |_| {}).await;
+
+        match res {
+            Err(LlmError::Status { status, body, .. }) => {
+                assert_eq!(status, 401);
+                assert_eq!(body, "bad api key");
+            }
+            other => panic!("expected a 401, got {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn tests_request_timeout_is_retried() {
+        let (url, requests) = start_stub_server(vec!["", "", ""]).await;
+
+        let res = request_completion(&Client::new(), &url, &stub_completion(), &stub_policy(), None, &mut |_| {}).await;
+
+        assert!(matches!(res, Err(LlmError::Timeout)));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
 }
@@ -0,0 +1,265 @@
+use crate::helpers::scaffold::workspaces_root;
+use crate::models::general::llm::{ChatCompletion, Message, Tool, ToolCall};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_DIR: &str = "llm_cache";
+
+const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+//only the early stages are replayed, generated code, fixes, tool loops and evals always go to the model
+const CACHED_FUNCTIONS: [&str; 3] = ["convert_user_input_to_goal", "print_project_scope", "print_site_urls"];
+
+static CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+static CACHE_HITS: AtomicUsize = AtomicUsize::new(0);
+
+static CACHE_MISSES: AtomicUsize = AtomicUsize::new(0);
+
+//--no-cache sends every call to the model
+pub fn disable_cache() {
+    CACHE_ENABLED.store(false, Ordering::SeqCst);
+}
+
+pub fn is_cache_enabled() -> bool {
+    CACHE_ENABLED.load(Ordering::SeqCst)
+}
+
+pub fn is_cached_function(ai_function_name: &str) -> bool {
+    CACHED_FUNCTIONS.contains(&ai_function_name)
+}
+
+pub fn record_cache_lookup(is_hit: bool) {
+    if is_hit {
+        CACHE_HITS.fetch_add(1, Ordering::SeqCst);
+    } else {
+        CACHE_MISSES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+//hits and misses of this process
+pub fn cache_stats() -> (usize, usize) {
+    (CACHE_HITS.load(Ordering::SeqCst), CACHE_MISSES.load(Ordering::SeqCst))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CachedCompletion {
+    pub created_at: u64,
+    pub model: String,
+    pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Serialize)]
+struct CacheKeyInput<'a> {
+    model: &'a str,
+    temperature: f32,
+    messages: &'a [Message],
+    //left out without tools so plain completions keep their keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a Vec<Tool>>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+//streaming options do not change the completion so they stay out of the key
+pub fn cache_key(chat_completion: &ChatCompletion) -> String {
+    let key_input: String = serde_json::to_string(&CacheKeyInput {
+        model: &chat_completion.model,
+        temperature: chat_completion.temperature,
+        messages: &chat_completion.messages,
+        tools: chat_completion.tools.as_ref(),
+    })
+    .expect("failed to encode cache key");
+
+    Sha256::digest(key_input.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct LlmCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    pub max_bytes: u64,
+}
+
+impl LlmCache {
+    //AUTO_GIPPITY_CACHE_DIR, AUTO_GIPPITY_CACHE_TTL_SECS and AUTO_GIPPITY_CACHE_MAX_BYTES override the defaults
+    pub fn from_env() -> Self {
+        let dir: PathBuf = match env::var("AUTO_GIPPITY_CACHE_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => workspaces_root().join(CACHE_DIR),
+        };
+        let ttl: Duration = env::var("AUTO_GIPPITY_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+        let max_bytes: u64 = env::var("AUTO_GIPPITY_CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_BYTES);
+
+        Self { dir, ttl, max_bytes }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    //expired and unreadable entries count as misses and are removed
+    pub fn get(&self, key: &str) -> Option<CachedCompletion> {
+        let path: PathBuf = self.entry_path(key);
+        let entry: Option<CachedCompletion> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|entry_str| serde_json::from_str(&entry_str).ok());
+
+        match entry {
+            Some(entry) if now_secs().saturating_sub(entry.created_at) < self.ttl.as_secs() => Some(entry),
+            _ => {
+                fs::remove_file(&path).ok();
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, content: &str, tool_calls: &[ToolCall], model: &str) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let entry: CachedCompletion = CachedCompletion {
+            created_at: now_secs(),
+            model: model.to_string(),
+            content: content.to_string(),
+            tool_calls: tool_calls.to_vec(),
+        };
+        let entry_str: String = serde_json::to_string(&entry).expect("failed to encode cache entry");
+        fs::write(self.entry_path(key), entry_str)?;
+
+        self.enforce_size_limit()
+    }
+
+    //drops the oldest entries until the cache fits
+    fn enforce_size_limit(&self) -> std::io::Result<()> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path: PathBuf = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                continue;
+            }
+            let metadata: fs::Metadata = fs::metadata(&path)?;
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        let mut total_bytes: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+
+        for (_, len, path) in entries {
+            if total_bytes <= self.max_bytes {
+                break;
+            }
+            remove_entry(&path)?;
+            total_bytes -= len;
+        }
+
+        Ok(())
+    }
+}
+
+fn remove_entry(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(temperature: f32) -> ChatCompletion {
+        ChatCompletion {
+            model: "gpt-3.5-turbo".to_string(),
+            messages: vec![Message {
+                role: "system".to_string(),
+                content: "print the project scope".to_string(),
+                ..Default::default()
+            }],
+            temperature,
+            stream: true,
+            stream_options: None,
+            tools: None,
+        }
+    }
+
+    fn test_cache(name: &str, max_bytes: u64) -> LlmCache {
+        let dir: PathBuf = env::temp_dir().join(format!("auto_gippity_cache_{}_{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        LlmCache {
+            dir,
+            ttl: Duration::from_secs(60),
+            max_bytes,
+        }
+    }
+
+    #[test]
+    fn tests_cache_key_covers_model_temperature_and_messages() {
+        let mut streamed: ChatCompletion = completion(0.1);
+        streamed.stream = false;
+
+        assert_eq!(cache_key(&completion(0.1)), cache_key(&streamed));
+        assert_ne!(cache_key(&completion(0.1)), cache_key(&completion(0.7)));
+        assert_eq!(cache_key(&completion(0.1)).len(), 64);
+
+        assert!(is_cached_function("print_project_scope"));
+        assert!(!is_cached_function("print_fixed_code"));
+    }
+
+    #[test]
+    fn tests_cache_expiry_and_size_limit() {
+        let cache: LlmCache = test_cache("expiry", 1024);
+
+        cache.put("fresh", "{\"is_crud_required\": true}", &[], "gpt-3.5-turbo").unwrap();
+        assert_eq!(cache.get("fresh").unwrap().content, "{\"is_crud_required\": true}");
+        assert!(cache.get("missing").is_none());
+
+        let expired: CachedCompletion = CachedCompletion {
+            created_at: now_secs() - 120,
+            model: "gpt-3.5-turbo".to_string(),
+            content: "old".to_string(),
+            tool_calls: vec![],
+        };
+        fs::write(cache.entry_path("expired"), serde_json::to_string(&expired).unwrap()).unwrap();
+        assert!(cache.get("expired").is_none());
+        assert!(!cache.entry_path("expired").exists());
+
+        //each entry is well over half the limit so only the newest one survives
+        let small_cache: LlmCache = LlmCache {
+            max_bytes: 700,
+            ..cache.clone()
+        };
+        small_cache.put("first", &"a".repeat(500), &[], "gpt-3.5-turbo").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        small_cache.put("second", &"b".repeat(500), &[], "gpt-3.5-turbo").unwrap();
+
+        assert!(small_cache.get("first").is_none());
+        assert!(small_cache.get("second").is_some());
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
@@ -1 +1,5 @@
 pub mod call_request;
+pub mod llm_cache;
+pub mod rate_limiter;
+pub mod retry;
+pub mod tool_calling;
@@ -0,0 +1,335 @@
+use crate::helpers::event_log::{emit, RunEvent};
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::time;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimits {
+    pub requests_per_minute: usize,
+    pub tokens_per_minute: u32,
+    pub max_in_flight: usize,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 60,
+            tokens_per_minute: 90_000,
+            max_in_flight: 4,
+        }
+    }
+}
+
+impl RateLimits {
+    //AUTO_GIPPITY_LLM_RPM, AUTO_GIPPITY_LLM_TPM and AUTO_GIPPITY_LLM_MAX_IN_FLIGHT override the defaults
+    pub fn from_env() -> Self {
+        limits_from(
+            env::var("AUTO_GIPPITY_LLM_RPM").ok().as_deref(),
+            env::var("AUTO_GIPPITY_LLM_TPM").ok().as_deref(),
+            env::var("AUTO_GIPPITY_LLM_MAX_IN_FLIGHT").ok().as_deref(),
+        )
+    }
+}
+
+fn limits_from(rpm: Option<&str>, tpm: Option<&str>, max_in_flight: Option<&str>) -> RateLimits {
+    let mut limits: RateLimits = RateLimits::default();
+
+    if let Some(rpm) = positive_limit("AUTO_GIPPITY_LLM_RPM", rpm) {
+        limits.requests_per_minute = rpm as usize;
+    }
+    if let Some(tpm) = positive_limit("AUTO_GIPPITY_LLM_TPM", tpm) {
+        limits.tokens_per_minute = tpm;
+    }
+    if let Some(max_in_flight) = positive_limit("AUTO_GIPPITY_LLM_MAX_IN_FLIGHT", max_in_flight) {
+        limits.max_in_flight = max_in_flight as usize;
+    }
+
+    limits
+}
+
+//a limit of zero would make every call wait forever, so it keeps the default
+fn positive_limit(name: &str, value: Option<&str>) -> Option<u32> {
+    let value: &str = value?;
+    match value.trim().parse::<u32>() {
+        Ok(limit) if limit > 0 => Some(limit),
+        _ => {
+            tracing::warn!(variable = name, value = %value, "ignoring rate limit, it has to be a positive number");
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueMetrics {
+    pub requests: usize,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    ticket: u64,
+    agent: String,
+    tokens: u32,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    waiting: VecDeque<Waiter>,
+    next_ticket: u64,
+    in_flight: usize,
+    //requests started in the last minute with their token counts
+    window: Vec<(u64, Instant, u32)>,
+    //agents served least recently go first so one busy agent cannot starve the others
+    last_served: HashMap<String, u64>,
+    served: u64,
+    metrics: QueueMetrics,
+}
+
+impl LimiterState {
+    fn next_up(&self) -> Option<&Waiter> {
+        self.waiting.iter().min_by_key(|waiter| {
+            let last_served: Option<u64> = self.last_served.get(&waiter.agent).copied();
+            (last_served.map(|served| served + 1).unwrap_or(0), waiter.ticket)
+        })
+    }
+
+    fn prune_window(&mut self, now: Instant) {
+        self.window.retain(|(_, started, _)| now.duration_since(*started) < WINDOW);
+    }
+}
+
+//shared by every LLM call in the process
+pub static RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimits::from_env()));
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    state: Mutex<LimiterState>,
+    notify: Notify,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(LimiterState::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        self.state.lock().unwrap().metrics.clone()
+    }
+
+    //waits for a turn in the queue and for room under every limit
+    pub async fn acquire(&self, agent: &str, estimated_tokens: u32) -> RatePermit<'_> {
+        let enqueued: Instant = Instant::now();
+        let ticket: u64 = {
+            let mut state = self.state.lock().unwrap();
+            let ticket: u64 = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiting.push_back(Waiter {
+                ticket,
+                agent: agent.to_string(),
+                tokens: estimated_tokens,
+            });
+            ticket
+        };
+        let mut queued: QueuedTicket = QueuedTicket {
+            limiter: self,
+            ticket: Some(ticket),
+        };
+
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            match self.try_start(ticket, enqueued) {
+                Ok(()) => {
+                    queued.ticket = None;
+                    let wait: Duration = enqueued.elapsed();
+
+                    emit(RunEvent::LlmQueued {
+                        agent: agent.to_string(),
+                        wait_ms: wait.as_millis() as u64,
+                    });
+                    return RatePermit { limiter: self, ticket };
+                }
+                Err(retry_in) => {
+                    tokio::select! {
+                        _ = notified => {}
+                        _ = time::sleep(retry_in) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    //Err holds how long until the window frees up again
+    fn try_start(&self, ticket: u64, enqueued: Instant) -> Result<(), Duration> {
+        let now: Instant = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.prune_window(now);
+
+        let Some(waiter) = state.next_up() else {
+            return Err(WINDOW);
+        };
+        if waiter.ticket != ticket || state.in_flight >= self.limits.max_in_flight {
+            return Err(WINDOW);
+        }
+
+        //a single request bigger than the token limit still goes through on an empty window
+        let window_tokens: u32 = state.window.iter().map(|(_, _, tokens)| tokens).sum();
+        let is_over_limit: bool = state.window.len() >= self.limits.requests_per_minute
+            || (!state.window.is_empty() && window_tokens + waiter.tokens > self.limits.tokens_per_minute);
+        if is_over_limit {
+            let oldest: Instant = state.window.iter().map(|(_, started, _)| *started).min().unwrap_or(now);
+            return Err(WINDOW.saturating_sub(now.duration_since(oldest)));
+        }
+
+        let position: usize = state.waiting.iter().position(|waiter| waiter.ticket == ticket).unwrap();
+        let waiter: Waiter = state.waiting.remove(position).unwrap();
+
+        state.in_flight += 1;
+        state.window.push((ticket, now, waiter.tokens));
+        let served: u64 = state.served;
+        state.last_served.insert(waiter.agent, served);
+        state.served += 1;
+
+        let wait: Duration = now.duration_since(enqueued);
+        state.metrics.requests += 1;
+        state.metrics.total_wait += wait;
+        state.metrics.max_wait = state.metrics.max_wait.max(wait);
+
+        //the next in line may fit as well
+        self.notify.notify_waiters();
+        Ok(())
+    }
+}
+
+//takes the ticket out of the queue when a waiting call is dropped
+struct QueuedTicket<'a> {
+    limiter: &'a RateLimiter,
+    ticket: Option<u64>,
+}
+
+impl Drop for QueuedTicket<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            self.limiter.state.lock().unwrap().waiting.retain(|waiter| waiter.ticket != ticket);
+            self.limiter.notify.notify_waiters();
+        }
+    }
+}
+
+pub struct RatePermit<'a> {
+    limiter: &'a RateLimiter,
+    ticket: u64,
+}
+
+impl RatePermit<'_> {
+    //swaps the estimate for the tokens the completion really used
+    pub fn record_tokens(&self, tokens: u32) {
+        let mut state = self.limiter.state.lock().unwrap();
+        if let Some(entry) = state.window.iter_mut().find(|(ticket, _, _)| *ticket == self.ticket) {
+            entry.2 = tokens;
+        }
+    }
+}
+
+impl Drop for RatePermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().in_flight -= 1;
+        self.limiter.notify.notify_waiters();
+    }
+}
+
+//roughly four characters per token
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.len() / 4) as u32 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn tests_zero_limits_are_ignored() {
+        let limits: RateLimits = limits_from(Some("0"), Some("many"), Some(" 2 "));
+
+        assert_eq!(limits.requests_per_minute, RateLimits::default().requests_per_minute);
+        assert_eq!(limits.tokens_per_minute, RateLimits::default().tokens_per_minute);
+        assert_eq!(limits.max_in_flight, 2);
+        assert_eq!(limits_from(None, None, None), RateLimits::default());
+    }
+
+    #[tokio::test]
+    async fn tests_queue_is_fair_across_agents() {
+        let limiter: Arc<RateLimiter> = Arc::new(RateLimiter::new(RateLimits {
+            requests_per_minute: 100,
+            tokens_per_minute: 100_000,
+            max_in_flight: 1,
+        }));
+        let order: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+        let first_permit: RatePermit = limiter.acquire("architect", 10).await;
+
+        let mut handles = vec![];
+        for name in ["architect-2", "architect-3", "backend-1"] {
+            let limiter: Arc<RateLimiter> = limiter.clone();
+            let order: Arc<Mutex<Vec<String>>> = order.clone();
+            let agent: String = name.split('-').next().unwrap().to_string();
+
+            handles.push(tokio::spawn(async move {
+                let _permit: RatePermit = limiter.acquire(&agent, 10).await;
+                order.lock().unwrap().push(name.to_string());
+                time::sleep(Duration::from_millis(10)).await;
+            }));
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(order.lock().unwrap().is_empty());
+        drop(first_permit);
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        //the backend has not been served yet so it skips ahead of the architect's backlog
+        assert_eq!(*order.lock().unwrap(), vec!["backend-1", "architect-2", "architect-3"]);
+
+        let metrics: QueueMetrics = limiter.metrics();
+        assert_eq!(metrics.requests, 4);
+        assert!(metrics.max_wait >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn tests_token_limit_blocks_until_window_frees() {
+        let limiter: RateLimiter = RateLimiter::new(RateLimits {
+            requests_per_minute: 100,
+            tokens_per_minute: 100,
+            max_in_flight: 10,
+        });
+
+        let permit: RatePermit = limiter.acquire("backend", 80).await;
+        permit.record_tokens(90);
+        drop(permit);
+
+        let blocked = time::timeout(Duration::from_millis(100), limiter.acquire("backend", 20)).await;
+        assert!(blocked.is_err());
+
+        //the dropped call left the queue so a request that fits is not stuck behind it
+        let fits = time::timeout(Duration::from_millis(100), limiter.acquire("backend", 5)).await;
+        assert!(fits.is_ok());
+    }
+}
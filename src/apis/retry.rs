@@ -0,0 +1,111 @@
+use crate::apis::call_request::LlmError;
+
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    //allowed wait for the response headers and between two chunks of a streamed completion
+    pub request_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    //AUTO_GIPPITY_LLM_RETRIES and AUTO_GIPPITY_LLM_TIMEOUT_SECS override the defaults
+    pub fn from_env() -> Self {
+        let mut policy: RetryPolicy = RetryPolicy::default();
+
+        if let Some(max_retries) = env::var("AUTO_GIPPITY_LLM_RETRIES").ok().and_then(|v| v.parse().ok()) {
+            policy.max_retries = max_retries;
+        }
+        if let Some(timeout_secs) = env::var("AUTO_GIPPITY_LLM_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()) {
+            policy.request_timeout = Duration::from_secs(timeout_secs);
+        }
+
+        policy
+    }
+
+    //exponential backoff with jitter between half and the full delay
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay: Duration = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        delay.mul_f64(0.5 + jitter_fraction() / 2.0)
+    }
+
+    //the server knows best how long to wait after a 429
+    pub fn retry_delay(&self, attempt: u32, error: &LlmError) -> Duration {
+        match error {
+            LlmError::Status {
+                retry_after: Some(retry_after),
+                ..
+            } => (*retry_after).min(self.max_delay),
+            _ => self.backoff_delay(attempt),
+        }
+    }
+}
+
+//only the delay-seconds form, HTTP dates fall back to the backoff
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+fn jitter_fraction() -> f64 {
+    let nanos: u32 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or(0);
+
+    (nanos % 1000) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_retry_delays() {
+        let policy: RetryPolicy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            request_timeout: Duration::from_secs(1),
+        };
+
+        for attempt in 0..5 {
+            let delay: Duration = policy.backoff_delay(attempt);
+            let full_delay: Duration = Duration::from_millis(100 * 2u64.pow(attempt)).min(policy.max_delay);
+            assert!(delay >= full_delay / 2 && delay <= full_delay);
+        }
+
+        let rate_limited: LlmError = LlmError::Status {
+            status: 429,
+            retry_after: parse_retry_after(" 2 "),
+            body: String::new(),
+        };
+        assert_eq!(parse_retry_after("2"), Some(Duration::from_secs(2)));
+        assert_eq!(policy.retry_delay(0, &rate_limited), Duration::from_millis(300));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("1e300"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+}
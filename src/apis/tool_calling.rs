@@ -0,0 +1,292 @@
+use crate::apis::call_request::{call_gpt_with_tools, AssistantReply};
+use crate::helpers::command_line::{finish_stream_progress, print_stream_progress};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::models::general::llm::{Message, Tool, ToolCall};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::Instant;
+
+//longer tool output is cut so one big file cannot fill the context window
+const MAX_TOOL_OUTPUT: usize = 8_000;
+
+pub type ToolError = Box<dyn std::error::Error + Send + Sync>;
+
+//a local function the model can call, the definition tells it the name and argument schema
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    fn definition(&self) -> Tool;
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError>;
+}
+
+//anything that answers a conversation with text or tool calls
+#[async_trait]
+pub trait ChatModel: Send + Sync {
+    async fn chat(
+        &self,
+        agent: &str,
+        messages: Vec<Message>,
+        tools: &[Tool],
+    ) -> Result<AssistantReply, Box<dyn std::error::Error + Send>>;
+}
+
+pub struct OpenAiChat;
+
+#[async_trait]
+impl ChatModel for OpenAiChat {
+    async fn chat(
+        &self,
+        agent: &str,
+        messages: Vec<Message>,
+        tools: &[Tool],
+    ) -> Result<AssistantReply, Box<dyn std::error::Error + Send>> {
+        let reply = call_gpt_with_tools(agent, messages, tools, false, &mut |progress| {
+            print_stream_progress("Calling tools", progress)
+        })
+        .await;
+        finish_stream_progress();
+        reply
+    }
+}
+
+#[derive(Default)]
+pub struct ToolSet {
+    handlers: Vec<Box<dyn ToolHandler>>,
+}
+
+impl ToolSet {
+    pub fn add(&mut self, handler: Box<dyn ToolHandler>) {
+        self.handlers.push(handler);
+    }
+
+    //narrows the set for a step that must not use every granted tool
+    pub fn retain(&mut self, names: &[&str]) {
+        self.handlers
+            .retain(|handler| names.contains(&handler.definition().function.name.as_str()));
+    }
+
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.handlers.iter().map(|handler| handler.definition()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ToolHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.definition().function.name == name)
+            .map(|handler| handler.as_ref())
+    }
+
+    //failures go back to the model as the tool result so it can correct itself
+    pub async fn execute(&self, agent: &str, tool_call: &ToolCall) -> Message {
+        let started: Instant = Instant::now();
+        let name: &str = &tool_call.function.name;
+
+        let result: Result<String, ToolError> = match (self.get(name), parse_arguments(&tool_call.function.arguments)) {
+            (None, _) => Err(format!("no tool named {}", name).into()),
+            (Some(_), Err(e)) => Err(e),
+            (Some(handler), Ok(arguments)) => handler.call(arguments).await,
+        };
+
+        emit(RunEvent::ToolCall {
+            agent: agent.to_string(),
+            tool: name.to_string(),
+            arguments: tool_call.function.arguments.clone(),
+            output: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+
+        let content: String = match result {
+            Ok(output) => truncate_output(output),
+            Err(e) => format!("error: {}", e),
+        };
+        Message {
+            role: "tool".to_string(),
+            content,
+            tool_calls: None,
+            tool_call_id: Some(tool_call.id.clone()),
+        }
+    }
+}
+
+//an empty argument string means a tool without parameters
+fn parse_arguments(arguments: &str) -> Result<Value, ToolError> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str(arguments).map_err(|e| format!("arguments are not valid JSON: {}", e).into())
+}
+
+fn truncate_output(mut output: String) -> String {
+    if output.len() <= MAX_TOOL_OUTPUT {
+        return output;
+    }
+    let mut end: usize = MAX_TOOL_OUTPUT;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    output.truncate(end);
+    output.push_str("\n[output truncated]");
+    output
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolLoopOutcome {
+    pub content: String,
+    //the whole conversation including tool calls and results
+    pub messages: Vec<Message>,
+    pub rounds: usize,
+    //false when the round limit was hit while the model was still calling tools
+    pub is_finished: bool,
+}
+
+//calls the model and runs the tools it asks for until it answers with text
+pub async fn run_tool_loop(
+    model: &dyn ChatModel,
+    agent: &str,
+    mut messages: Vec<Message>,
+    tools: &ToolSet,
+    max_rounds: usize,
+) -> Result<ToolLoopOutcome, Box<dyn std::error::Error + Send>> {
+    let definitions: Vec<Tool> = tools.definitions();
+
+    for round in 1..=max_rounds {
+        let reply: AssistantReply = model.chat(agent, messages.clone(), &definitions).await?;
+        messages.push(reply.to_message());
+
+        if reply.tool_calls.is_empty() {
+            return Ok(ToolLoopOutcome {
+                content: reply.content,
+                messages,
+                rounds: round,
+                is_finished: true,
+            });
+        }
+
+        for tool_call in &reply.tool_calls {
+            messages.push(tools.execute(agent, tool_call).await);
+        }
+    }
+
+    Ok(ToolLoopOutcome {
+        content: String::new(),
+        messages,
+        rounds: max_rounds,
+        is_finished: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::general::llm::FunctionCall;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    struct UppercaseTool;
+
+    #[async_trait]
+    impl ToolHandler for UppercaseTool {
+        fn definition(&self) -> Tool {
+            Tool::function(
+                "uppercase",
+                "Uppercases a text",
+                json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+            )
+        }
+
+        async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+            let text: &str = arguments["text"].as_str().ok_or("text is missing")?;
+            Ok(text.to_uppercase())
+        }
+    }
+
+    //answers with the scripted replies in order and keeps what it was sent
+    struct ScriptedModel {
+        replies: Mutex<VecDeque<AssistantReply>>,
+        received: Mutex<Vec<Vec<Message>>>,
+    }
+
+    #[async_trait]
+    impl ChatModel for ScriptedModel {
+        async fn chat(
+            &self,
+            _agent: &str,
+            messages: Vec<Message>,
+            tools: &[Tool],
+        ) -> Result<AssistantReply, Box<dyn std::error::Error + Send>> {
+            assert_eq!(tools[0].function.name, "uppercase");
+            self.received.lock().unwrap().push(messages);
+            Ok(self.replies.lock().unwrap().pop_front().unwrap_or_default())
+        }
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_tool_loop_feeds_results_back() {
+        let model: ScriptedModel = ScriptedModel {
+            replies: Mutex::new(VecDeque::from([
+                AssistantReply {
+                    content: String::new(),
+                    tool_calls: vec![
+                        tool_call("call_1", "uppercase", r#"{"text": "done"}"#),
+                        tool_call("call_2", "delete_everything", "{}"),
+                    ],
+                },
+                AssistantReply {
+                    content: "DONE".to_string(),
+                    tool_calls: vec![],
+                },
+            ])),
+            received: Mutex::new(vec![]),
+        };
+        let mut tools: ToolSet = ToolSet::default();
+        tools.add(Box::new(UppercaseTool));
+
+        let messages: Vec<Message> = vec![Message {
+            role: "user".to_string(),
+            content: "uppercase done".to_string(),
+            ..Default::default()
+        }];
+        let outcome: ToolLoopOutcome = run_tool_loop(&model, "test", messages, &tools, 3).await.unwrap();
+
+        assert_eq!(outcome.content, "DONE");
+        assert_eq!(outcome.rounds, 2);
+        assert!(outcome.is_finished);
+
+        let second_call: Vec<Message> = model.received.lock().unwrap()[1].clone();
+        assert_eq!(second_call[1].tool_calls.as_ref().unwrap().len(), 2);
+        assert_eq!(second_call[2].role, "tool");
+        assert_eq!(second_call[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(second_call[2].content, "DONE");
+        assert_eq!(second_call[3].content, "error: no tool named delete_everything");
+
+        //a model that never stops calling tools is cut off
+        let looping: ScriptedModel = ScriptedModel {
+            replies: Mutex::new(VecDeque::from(vec![
+                AssistantReply {
+                    content: String::new(),
+                    tool_calls: vec![tool_call("call_3", "uppercase", "not json")],
+                };
+                2
+            ])),
+            received: Mutex::new(vec![]),
+        };
+        let cut_off: ToolLoopOutcome = run_tool_loop(&looping, "test", vec![], &tools, 2).await.unwrap();
+        assert!(!cut_off.is_finished);
+        assert_eq!(cut_off.messages.len(), 4);
+        assert!(looping.received.lock().unwrap()[1][1].content.starts_with("error: arguments are not valid JSON"));
+    }
+}
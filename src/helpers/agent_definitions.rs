@@ -0,0 +1,183 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::models::agents::agent_traits::CODE_REVIEW;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const AGENTS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/agents");
+
+//the one input that is not an artefact
+pub const PROJECT_DESCRIPTION: &str = "project_description";
+
+//each prompt is the description of the function the model acts out in that state
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AgentPrompts {
+    pub discovery: Option<String>,
+    pub working: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AgentDefinition {
+    pub position: String,
+    pub objective: String,
+    //artefact names, any name works so agents can hand each other new kinds of data
+    pub inputs: Vec<String>,
+    pub output: Option<String>,
+    //the working prompt may call the tools tool_permissions.json grants the position
+    #[serde(default)]
+    pub uses_tools: bool,
+    pub prompts: AgentPrompts,
+    //run in the generated project once the output is written, any failure fails the agent
+    #[serde(default)]
+    pub validation: Vec<ValidationCommand>,
+}
+
+impl AgentDefinition {
+    //named like an ai_function so prompt templates and examples can be keyed on it
+    pub fn function_name(&self, state: &str) -> String {
+        let position: String = self
+            .position
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("print_{}_{}", position, state)
+    }
+
+    //the same shape an #[ai_function] prints, so the prompt registry handles both alike
+    pub fn function_spec(&self, state: &str, prompt: &str) -> String {
+        let doc_lines: String = prompt
+            .lines()
+            .map(|line| format!("/// {}\n", line.trim()))
+            .collect();
+        format!(
+            "{}fn {}(_context: &str) {{\n    println!(OUTPUT)\n}}",
+            doc_lines,
+            self.function_name(state)
+        )
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.position.trim().is_empty() {
+            return Err("position is empty".to_string());
+        }
+
+        if self.inputs.iter().chain(self.output.iter()).any(|name| name.trim().is_empty()) {
+            return Err("artefact names cannot be empty".to_string());
+        }
+        if self.output.as_deref() == Some(PROJECT_DESCRIPTION) {
+            return Err("the project description cannot be an output".to_string());
+        }
+
+        syn::parse_str::<syn::ItemFn>(&self.function_spec("working", &self.prompts.working))
+            .map_err(|e| format!("position cannot name a function: {}", e))?;
+        Ok(())
+    }
+
+    //reviewers run inside the backend's review step so their findings can still block the code
+    pub fn is_code_reviewer(&self) -> bool {
+        self.output.as_deref() == Some(CODE_REVIEW)
+    }
+}
+
+//every *.toml file is one agent, scheduled in file name order
+fn definition_paths(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path: PathBuf = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) == Some("toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn load_agent_definition(path: &Path) -> Result<AgentDefinition, Box<dyn std::error::Error>> {
+    let definition: AgentDefinition = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("invalid agent definition {}: {}", path.display(), e))?;
+    definition
+        .validate()
+        .map_err(|e| format!("invalid agent definition {}: {}", path.display(), e))?;
+    Ok(definition)
+}
+
+//one broken file should not take the other agents down with it
+fn load_valid_agent_definitions(dir: &Path) -> Vec<AgentDefinition> {
+    let paths: Vec<PathBuf> = match definition_paths(dir) {
+        Ok(paths) => paths,
+        Err(e) => {
+            let error_msg: String = format!("Unable to read agent definitions in {}: {}", dir.display(), e);
+            PrintCommand::Issue.print_agent_message("Agent Definitions", error_msg.as_str());
+            return vec![];
+        }
+    };
+
+    let mut definitions: Vec<AgentDefinition> = vec![];
+    for path in paths {
+        match load_agent_definition(&path) {
+            Ok(definition) => definitions.push(definition),
+            Err(e) => {
+                let error_msg: String = format!("Skipping agent: {}", e);
+                PrintCommand::Issue.print_agent_message("Agent Definitions", error_msg.as_str());
+            }
+        }
+    }
+    definitions
+}
+
+//AUTO_GIPPITY_AGENTS points at a custom agent directory, a missing one means built-in agents only
+pub fn load_default_agent_definitions() -> Vec<AgentDefinition> {
+    let dir: String = env::var("AUTO_GIPPITY_AGENTS").unwrap_or(AGENTS_PATH.to_string());
+    if !Path::new(&dir).exists() {
+        return vec![];
+    }
+    load_valid_agent_definitions(Path::new(&dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::prompts::ai_function_name;
+
+    #[test]
+    fn tests_loading_agent_definitions() {
+        let definitions: Vec<AgentDefinition> = load_valid_agent_definitions(Path::new(AGENTS_PATH));
+        let checker: &AgentDefinition = definitions
+            .iter()
+            .find(|definition| definition.position == "Compliance Checker")
+            .expect("bundled compliance checker is missing");
+
+        let spec: String = checker.function_spec("working", &checker.prompts.working);
+        assert_eq!(ai_function_name(&spec), "print_compliance_checker_working");
+        assert!(checker.is_code_reviewer());
+
+        let dir: PathBuf = std::env::temp_dir().join(format!("auto_gippity_agents_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("broken.toml"),
+            "position = \"Broken\"\nobjective = \"none\"\ninputs = []\noutput = \"project_description\"\n[prompts]\nworking = \"prints\"\n",
+        )
+        .unwrap();
+        fs::copy(Path::new(AGENTS_PATH).join("compliance_checker.toml"), dir.join("checker.toml")).unwrap();
+        let error: String = load_agent_definition(&dir.join("broken.toml")).unwrap_err().to_string();
+        let valid: Vec<AgentDefinition> = load_valid_agent_definitions(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(error.ends_with("the project description cannot be an output"));
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].position, "Compliance Checker");
+    }
+}
@@ -0,0 +1,482 @@
+use crate::apis::tool_calling::{ToolError, ToolHandler, ToolSet};
+use crate::helpers::build_cache::cargo_command;
+use crate::helpers::general::check_status_code;
+use crate::helpers::telemetry::traced_output;
+use crate::models::general::llm::Tool;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Output;
+use std::time::Duration;
+
+pub const TOOL_PERMISSIONS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tool_permissions.json");
+
+//the generated server listens here while it is being tested
+pub const LOCAL_SERVER_URL: &str = "http://localhost:8080";
+
+//the template owns the manifests, cargo config and build setup, the model only touches the code
+const WRITABLE_DIRS: [&str; 2] = ["src", "tests"];
+
+const SKIPPED_DIRS: [&str; 2] = ["target", ".git"];
+
+const MAX_LISTED_FILES: usize = 200;
+
+const MAX_SEARCH_MATCHES: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    ReadFiles,
+    WriteFiles,
+    RunBuild,
+    LocalHttp,
+    ExternalHttp,
+}
+
+//which tools each agent may be offered, keyed by agent position
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolPermissions {
+    #[serde(default)]
+    pub agents: HashMap<String, Vec<ToolPermission>>,
+}
+
+impl Default for ToolPermissions {
+    fn default() -> Self {
+        let agents: HashMap<String, Vec<ToolPermission>> = HashMap::from([
+            (
+                "Backend Developer".to_string(),
+                vec![
+                    ToolPermission::ReadFiles,
+                    ToolPermission::WriteFiles,
+                    ToolPermission::RunBuild,
+                    ToolPermission::LocalHttp,
+                ],
+            ),
+        ]);
+
+        Self { agents }
+    }
+}
+
+impl ToolPermissions {
+    pub fn granted(&self, agent: &str) -> &[ToolPermission] {
+        self.agents.get(agent).map(|permissions| permissions.as_slice()).unwrap_or_default()
+    }
+}
+
+pub fn load_tool_permissions() -> ToolPermissions {
+    match fs::read_to_string(TOOL_PERMISSIONS_PATH) {
+        Ok(permissions_str) => {
+            serde_json::from_str(&permissions_str).expect("failed to decode tool permissions file")
+        }
+        Err(_) => ToolPermissions::default(),
+    }
+}
+
+//every tool the registry knows, with the permission it needs
+pub fn registered_tools(project_dir: &Path, is_offline: bool) -> Vec<(ToolPermission, Box<dyn ToolHandler>)> {
+    let root: PathBuf = project_dir.to_path_buf();
+    let client: Client = Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    vec![
+        (ToolPermission::ReadFiles, Box::new(ReadFileTool { root: root.clone() })),
+        (ToolPermission::ReadFiles, Box::new(ListFilesTool { root: root.clone() })),
+        (ToolPermission::ReadFiles, Box::new(SearchCodeTool { root: root.clone() })),
+        (ToolPermission::WriteFiles, Box::new(WriteFileTool { root: root.clone() })),
+        (
+            ToolPermission::RunBuild,
+            Box::new(CargoTool {
+                root: root.clone(),
+                subcommand: "check",
+                is_offline,
+            }),
+        ),
+        (
+            ToolPermission::RunBuild,
+            Box::new(CargoTool {
+                root,
+                subcommand: "test",
+                is_offline,
+            }),
+        ),
+        (ToolPermission::LocalHttp, Box::new(LocalHttpGetTool { client: client.clone() })),
+        (ToolPermission::ExternalHttp, Box::new(CheckUrlTool { client })),
+    ]
+}
+
+//only the tools the agent has been granted are offered to the model
+pub fn agent_toolset(agent: &str, permissions: &ToolPermissions, project_dir: &Path, is_offline: bool) -> ToolSet {
+    let granted: &[ToolPermission] = permissions.granted(agent);
+
+    let mut tools: ToolSet = ToolSet::default();
+    for (permission, handler) in registered_tools(project_dir, is_offline) {
+        if granted.contains(&permission) {
+            tools.add(handler);
+        }
+    }
+    tools
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, ToolError> {
+    arguments[name]
+        .as_str()
+        .ok_or_else(|| format!("{} is missing", name).into())
+}
+
+//relative paths below the project only, no absolute paths and no ..
+pub fn resolve_project_path(root: &Path, path: &str) -> Result<PathBuf, ToolError> {
+    let relative: &Path = Path::new(path);
+    let is_inside: bool = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if !is_inside {
+        return Err(format!("{} is outside the project", path).into());
+    }
+    Ok(root.join(relative))
+}
+
+fn project_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        let name: &str = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name) {
+                project_files(root, &path, files)?;
+            }
+        } else {
+            files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+pub struct ReadFileTool {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl ToolHandler for ReadFileTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "read_file",
+            "Reads a text file of the project.",
+            json!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "relative to the project root, e.g. src/main.rs"}},
+                "required": ["path"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let path: PathBuf = resolve_project_path(&self.root, string_argument(&arguments, "path")?)?;
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+pub struct ListFilesTool {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl ToolHandler for ListFilesTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "list_files",
+            "Lists the files of the project, or of one directory in it.",
+            json!({
+                "type": "object",
+                "properties": {"path": {"type": "string", "description": "directory relative to the project root, empty for all files"}}
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let dir: PathBuf = resolve_project_path(&self.root, arguments["path"].as_str().unwrap_or_default())?;
+
+        let mut files: Vec<PathBuf> = vec![];
+        project_files(&self.root, &dir, &mut files)?;
+
+        let listed: Vec<String> = files
+            .iter()
+            .take(MAX_LISTED_FILES)
+            .map(|file| file.display().to_string())
+            .collect();
+        Ok(listed.join("\n"))
+    }
+}
+
+pub struct SearchCodeTool {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl ToolHandler for SearchCodeTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "search_code",
+            "Finds the lines of the project files which contain a text, as path:line: text.",
+            json!({
+                "type": "object",
+                "properties": {"text": {"type": "string", "description": "the exact text to look for"}},
+                "required": ["text"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let text: &str = string_argument(&arguments, "text")?;
+
+        let mut files: Vec<PathBuf> = vec![];
+        project_files(&self.root, &self.root, &mut files)?;
+
+        let mut matches: Vec<String> = vec![];
+        for file in files {
+            //binary files are skipped
+            let Ok(contents) = fs::read_to_string(self.root.join(&file)) else {
+                continue;
+            };
+            for (line_number, line) in contents.lines().enumerate() {
+                if line.contains(text) {
+                    matches.push(format!("{}:{}: {}", file.display(), line_number + 1, line.trim()));
+                }
+            }
+        }
+
+        if matches.is_empty() {
+            return Ok("no matches".to_string());
+        }
+        matches.truncate(MAX_SEARCH_MATCHES);
+        Ok(matches.join("\n"))
+    }
+}
+
+pub struct WriteFileTool {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl ToolHandler for WriteFileTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "write_file",
+            "Replaces the whole contents of a .rs file under src/ or tests/, or creates it. No other file can be written.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "relative to the project root, e.g. src/main.rs"},
+                    "content": {"type": "string", "description": "the complete new file contents"}
+                },
+                "required": ["path", "content"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let relative_path: &str = string_argument(&arguments, "path")?;
+        let content: &str = string_argument(&arguments, "content")?;
+        let path: PathBuf = resolve_project_path(&self.root, relative_path)?;
+
+        let mut components = Path::new(relative_path)
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)));
+        let top_dir: Option<&str> = components.next().and_then(|component| component.as_os_str().to_str());
+        let is_rust_file: bool = path.extension().is_some_and(|extension| extension == "rs");
+        let is_writable: bool = top_dir.is_some_and(|top_dir| WRITABLE_DIRS.contains(&top_dir)) && components.next().is_some();
+        if !is_writable || !is_rust_file {
+            return Err(format!("{} is managed by the code template, only .rs files under src/ and tests/ can be written", relative_path).into());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        Ok(format!("wrote {} bytes to {}", content.len(), relative_path))
+    }
+}
+
+pub struct CargoTool {
+    pub root: PathBuf,
+    pub subcommand: &'static str,
+    pub is_offline: bool,
+}
+
+#[async_trait]
+impl ToolHandler for CargoTool {
+    fn definition(&self) -> Tool {
+        let description: String = format!(
+            "Runs cargo {} in the project and returns whether it succeeded with the compiler output.",
+            self.subcommand
+        );
+        Tool::function(
+            &format!("cargo_{}", self.subcommand),
+            &description,
+            json!({"type": "object", "properties": {}}),
+        )
+    }
+
+    async fn call(&self, _arguments: Value) -> Result<String, ToolError> {
+        let root: PathBuf = self.root.clone();
+        let subcommand: &'static str = self.subcommand;
+        let is_offline: bool = self.is_offline;
+
+        let output: Output =
+            tokio::task::spawn_blocking(move || traced_output(&mut cargo_command(subcommand, &root, is_offline)))
+                .await??;
+
+        let outcome: &str = if output.status.success() { "succeeded" } else { "failed" };
+        Ok(format!(
+            "cargo {} {}\n{}{}",
+            subcommand,
+            outcome,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+pub struct LocalHttpGetTool {
+    pub client: Client,
+}
+
+#[async_trait]
+impl ToolHandler for LocalHttpGetTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "http_get",
+            "Sends a GET request to the project's web server on localhost:8080 and returns the status and body. Only answers while the server is running.",
+            json!({
+                "type": "object",
+                "properties": {"route": {"type": "string", "description": "the route including any query string, e.g. /items/1"}},
+                "required": ["route"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let route: &str = string_argument(&arguments, "route")?;
+        if !route.starts_with('/') {
+            return Err("route must start with /".into());
+        }
+
+        let response: reqwest::Response = self.client.get(format!("{}{}", LOCAL_SERVER_URL, route)).send().await?;
+        let status: u16 = response.status().as_u16();
+        let body: String = response.text().await?;
+        Ok(format!("status {}\n{}", status, body))
+    }
+}
+
+//lets the model try an external endpoint before it relies on it
+//it can reach any host, so no agent is granted external_http unless the permissions file opts in
+pub struct CheckUrlTool {
+    pub client: Client,
+}
+
+#[async_trait]
+impl ToolHandler for CheckUrlTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "check_url",
+            "Sends a GET request to a public URL and returns the HTTP status code. Use it to confirm an endpoint works without an API key.",
+            json!({
+                "type": "object",
+                "properties": {"url": {"type": "string", "description": "the full URL including the query string"}},
+                "required": ["url"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, ToolError> {
+        let url: &str = string_argument(&arguments, "url")?;
+        let status_code: u16 = check_status_code(&self.client, url).await?;
+        Ok(format!("status {}", status_code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_names(tools: &ToolSet) -> Vec<String> {
+        tools
+            .definitions()
+            .into_iter()
+            .map(|tool| tool.function.name)
+            .collect()
+    }
+
+    #[test]
+    fn tests_toolsets_follow_agent_permissions() {
+        let permissions: ToolPermissions = ToolPermissions::default();
+        let project_dir: &Path = Path::new("/tmp/project");
+
+        assert_eq!(
+            tool_names(&agent_toolset("Backend Developer", &permissions, project_dir, false)),
+            vec!["read_file", "list_files", "search_code", "write_file", "cargo_check", "cargo_test", "http_get"]
+        );
+        assert!(tool_names(&agent_toolset("Solutions Architect", &permissions, project_dir, false)).is_empty());
+        assert!(tool_names(&agent_toolset("Code Reviewer", &permissions, project_dir, false)).is_empty());
+
+        let opted_in: ToolPermissions = ToolPermissions {
+            agents: HashMap::from([("Solutions Architect".to_string(), vec![ToolPermission::ExternalHttp])]),
+        };
+        assert_eq!(
+            tool_names(&agent_toolset("Solutions Architect", &opted_in, project_dir, false)),
+            vec!["check_url"]
+        );
+
+        let mut fix_tools: ToolSet = agent_toolset("Backend Developer", &permissions, project_dir, false);
+        fix_tools.retain(&["write_file", "cargo_check", "check_url"]);
+        assert_eq!(tool_names(&fix_tools), vec!["write_file", "cargo_check"]);
+
+        let bundled: ToolPermissions = load_tool_permissions();
+        assert_eq!(bundled, permissions);
+    }
+
+    #[tokio::test]
+    async fn tests_file_tools_stay_inside_the_project() {
+        let root: PathBuf = std::env::temp_dir().join(format!("auto_gippity_tools_{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+
+        let write_file: WriteFileTool = WriteFileTool { root: root.clone() };
+        write_file
+            .call(json!({"path": "src/main.rs", "content": "fn main() {\n    serve();\n}\n"}))
+            .await
+            .unwrap();
+
+        for escape in ["../outside.rs", "/etc/passwd", "src/../../outside.rs"] {
+            let res = write_file.call(json!({"path": escape, "content": ""})).await;
+            assert!(res.unwrap_err().to_string().contains("outside the project"));
+        }
+        for protected in ["Cargo.toml", ".cargo/config.toml", "build.rs", "rust-toolchain.toml", "src", "src/payload.txt"] {
+            assert!(write_file.call(json!({"path": protected, "content": ""})).await.is_err());
+        }
+        assert!(!root.join(".cargo").exists());
+        assert_eq!(fs::read_to_string(root.join("Cargo.toml")).unwrap(), "[package]\n");
+
+        let read_file: ReadFileTool = ReadFileTool { root: root.clone() };
+        assert!(read_file.call(json!({"path": "src/main.rs"})).await.unwrap().contains("serve();"));
+
+        let list_files: ListFilesTool = ListFilesTool { root: root.clone() };
+        assert_eq!(list_files.call(json!({})).await.unwrap(), "Cargo.toml\nsrc/main.rs");
+
+        let search_code: SearchCodeTool = SearchCodeTool { root: root.clone() };
+        assert_eq!(search_code.call(json!({"text": "serve"})).await.unwrap(), "src/main.rs:2: serve();");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use crate::helpers::scaffold::{scaffold_project, shared_target_dir, toml_path, workspaces_root};
+use crate::helpers::telemetry::traced_output;
+use crate::helpers::templates::CodeTemplate;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const VENDOR_DIR: &str = "vendor";
+
+const WARM_UP_DIR: &str = "warm_up";
+
+//vendored sources and the lock file they were vendored for, one set per template
+pub fn vendor_dir(template: &CodeTemplate) -> PathBuf {
+    workspaces_root().join(VENDOR_DIR).join(&template.manifest.name)
+}
+
+pub fn vendored_lock_file(template: &CodeTemplate) -> PathBuf {
+    vendor_dir(template).join("Cargo.lock")
+}
+
+pub fn is_template_vendored(template: &CodeTemplate) -> bool {
+    vendored_lock_file(template).exists()
+}
+
+//AUTO_GIPPITY_OFFLINE=1 forces --offline even for templates which were never vendored
+pub fn is_offline_forced() -> bool {
+    matches!(
+        env::var("AUTO_GIPPITY_OFFLINE").as_deref(),
+        Ok("1") | Ok("true") | Ok("yes")
+    )
+}
+
+pub fn is_offline_build(template: &CodeTemplate) -> bool {
+    is_offline_forced() || is_template_vendored(template)
+}
+
+//the .cargo/config.toml source table which points crates.io at the vendored sources
+pub fn vendor_source_config(template: &CodeTemplate) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let mut crates_io: toml::Table = toml::Table::new();
+    crates_io.insert("replace-with".to_string(), toml::Value::String("vendored-sources".to_string()));
+
+    let mut vendored_sources: toml::Table = toml::Table::new();
+    vendored_sources.insert("directory".to_string(), toml_path(&vendor_dir(template))?);
+
+    let mut source: toml::Table = toml::Table::new();
+    source.insert("crates-io".to_string(), toml::Value::Table(crates_io));
+    source.insert("vendored-sources".to_string(), toml::Value::Table(vendored_sources));
+    Ok(source)
+}
+
+pub fn cargo_command(subcommand: &str, project_dir: &Path, is_offline: bool) -> Command {
+    let mut command: Command = Command::new("cargo");
+    command
+        .arg(subcommand)
+        .current_dir(project_dir)
+        .env("CARGO_TARGET_DIR", shared_target_dir())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if is_offline {
+        command.arg("--offline");
+    }
+
+    command
+}
+
+fn run_cargo(command: &mut Command) -> Result<Output, Box<dyn std::error::Error>> {
+    let output: Output = traced_output(command)?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string().into());
+    }
+    Ok(output)
+}
+
+//vendors the template's dependencies and prebuilds them into the shared target directory
+pub fn warm_up_template(template: &CodeTemplate) -> Result<(), Box<dyn std::error::Error>> {
+    let project_dir: PathBuf = workspaces_root().join(WARM_UP_DIR).join(&template.manifest.name);
+    scaffold_project(&project_dir, template)?;
+
+    if !is_template_vendored(template) {
+        let vendor_path: PathBuf = vendor_dir(template);
+        fs::create_dir_all(&vendor_path)?;
+
+        run_cargo(&mut cargo_command("generate-lockfile", &project_dir, false))?;
+        run_cargo(cargo_command("vendor", &project_dir, false).arg(&vendor_path))?;
+
+        fs::copy(project_dir.join("Cargo.lock"), vendored_lock_file(template))?;
+    }
+
+    //scaffold again so the warm-up project builds from the vendored sources like a real run
+    scaffold_project(&project_dir, template)?;
+    run_cargo(&mut cargo_command("build", &project_dir, true))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::templates::{TemplateRegistry, DEFAULT_TEMPLATE, TEMPLATES_PATH};
+    use std::ffi::OsStr;
+
+    #[test]
+    fn tests_cargo_command_uses_shared_target_dir() {
+        let command: Command = cargo_command("build", Path::new("/tmp/project"), true);
+
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args, vec![OsStr::new("build"), OsStr::new("--offline")]);
+        assert_eq!(command.get_current_dir(), Some(Path::new("/tmp/project")));
+
+        let target_dir = command
+            .get_envs()
+            .find(|(key, _)| *key == OsStr::new("CARGO_TARGET_DIR"))
+            .and_then(|(_, value)| value);
+        assert_eq!(target_dir, Some(shared_target_dir().as_os_str()));
+    }
+
+    #[test]
+    fn tests_vendor_source_config_is_valid_toml() {
+        let registry: TemplateRegistry = TemplateRegistry::load(Path::new(TEMPLATES_PATH)).unwrap();
+        let template: &CodeTemplate = registry.get(DEFAULT_TEMPLATE).unwrap();
+
+        let config_str: String = toml::to_string(&vendor_source_config(template).unwrap()).unwrap();
+        let config: toml::Table = config_str.parse().unwrap();
+        assert_eq!(config["crates-io"]["replace-with"].as_str(), Some("vendored-sources"));
+        assert_eq!(config["vendored-sources"]["directory"].as_str(), vendor_dir(template).to_str());
+    }
+}
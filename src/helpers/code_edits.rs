@@ -0,0 +1,175 @@
+use std::fmt;
+
+const SEARCH_MARKER: &str = "<<<<<<< SEARCH";
+const DIVIDER_MARKER: &str = "=======";
+const REPLACE_MARKER: &str = ">>>>>>> REPLACE";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EditBlock {
+    pub search: String,
+    pub replace: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EditError {
+    NoEdits,
+    MalformedBlock(usize),
+    SearchNotFound(usize),
+    AmbiguousSearch(usize),
+    InvalidResult(String),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoEdits => write!(f, "response contains no search/replace blocks"),
+            Self::MalformedBlock(index) => write!(f, "edit block {} is not closed properly", index + 1),
+            Self::SearchNotFound(index) => {
+                write!(f, "search text of edit block {} was not found in the code", index + 1)
+            }
+            Self::AmbiguousSearch(index) => {
+                write!(f, "search text of edit block {} matches more than once", index + 1)
+            }
+            Self::InvalidResult(e) => write!(f, "edited code is not valid rust: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+pub fn parse_edit_blocks(response: &str) -> Result<Vec<EditBlock>, EditError> {
+    let mut blocks: Vec<EditBlock> = vec![];
+    let mut lines = response.lines();
+
+    while let Some(line) = lines.next() {
+        if line.trim() != SEARCH_MARKER {
+            continue;
+        }
+
+        let index: usize = blocks.len();
+        let mut search: Vec<&str> = vec![];
+        let mut replace: Vec<&str> = vec![];
+        let mut is_divided: bool = false;
+        let mut is_closed: bool = false;
+
+        for line in lines.by_ref() {
+            match line.trim() {
+                DIVIDER_MARKER if !is_divided => is_divided = true,
+                REPLACE_MARKER if is_divided => {
+                    is_closed = true;
+                    break;
+                }
+                _ if is_divided => replace.push(line),
+                _ => search.push(line),
+            }
+        }
+
+        if !is_closed || search.iter().all(|line| line.trim().is_empty()) {
+            return Err(EditError::MalformedBlock(index));
+        }
+
+        blocks.push(EditBlock {
+            search: search.join("\n"),
+            replace: replace.join("\n"),
+        });
+    }
+
+    if blocks.is_empty() {
+        return Err(EditError::NoEdits);
+    }
+
+    Ok(blocks)
+}
+
+//falls back to matching with trailing whitespace ignored, models often lose it
+fn find_unique_match(code: &str, search: &str, index: usize) -> Result<(usize, usize), EditError> {
+    let matches: Vec<(usize, &str)> = code.match_indices(search).collect();
+    match matches.len() {
+        1 => return Ok((matches[0].0, matches[0].0 + search.len())),
+        0 => {}
+        _ => return Err(EditError::AmbiguousSearch(index)),
+    }
+
+    let code_lines: Vec<&str> = code.split('\n').collect();
+    let search_lines: Vec<&str> = search.lines().map(|line| line.trim_end()).collect();
+    let mut found: Vec<(usize, usize)> = vec![];
+
+    for start in 0..code_lines.len() {
+        let end: usize = start + search_lines.len();
+        if end > code_lines.len() {
+            break;
+        }
+
+        let is_match: bool = code_lines[start..end]
+            .iter()
+            .zip(search_lines.iter())
+            .all(|(code_line, search_line)| code_line.trim_end() == *search_line);
+
+        if is_match {
+            let byte_start: usize = code_lines[..start].iter().map(|line| line.len() + 1).sum();
+            let byte_end: usize =
+                byte_start + code_lines[start..end].join("\n").len();
+            found.push((byte_start, byte_end));
+        }
+    }
+
+    match found.len() {
+        1 => Ok(found[0]),
+        0 => Err(EditError::SearchNotFound(index)),
+        _ => Err(EditError::AmbiguousSearch(index)),
+    }
+}
+
+pub fn apply_edit_blocks(code: &str, blocks: &[EditBlock]) -> Result<String, EditError> {
+    let mut edited_code: String = code.to_string();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let (start, end) = find_unique_match(&edited_code, &block.search, index)?;
+        edited_code.replace_range(start..end, &block.replace);
+    }
+
+    Ok(edited_code)
+}
+
+//parses the model response, applies every block and checks the result still parses
+pub fn apply_code_edits(code: &str, response: &str) -> Result<String, EditError> {
+    let blocks: Vec<EditBlock> = parse_edit_blocks(response)?;
+    let edited_code: String = apply_edit_blocks(code, &blocks)?;
+
+    syn::parse_file(&edited_code).map_err(|e| EditError::InvalidResult(e.to_string()))?;
+
+    Ok(edited_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "fn main() {\n    let port = 8080;   \n    println!(\"{}\", port);\n}\n";
+
+    #[test]
+    fn tests_apply_code_edits() {
+        let response: &str = "Here you go\n<<<<<<< SEARCH\n    let port = 8080;\n=======\n    let port = 3000;\n>>>>>>> REPLACE\n";
+
+        let edited_code: String = apply_code_edits(CODE, response).unwrap();
+
+        assert_eq!(edited_code, "fn main() {\n    let port = 3000;   \n    println!(\"{}\", port);\n}\n");
+    }
+
+    #[test]
+    fn tests_edit_errors() {
+        assert_eq!(apply_code_edits(CODE, "fn main() {}"), Err(EditError::NoEdits));
+
+        let unclosed: &str = "<<<<<<< SEARCH\nfn main() {\n=======\nfn start() {\n";
+        assert_eq!(apply_code_edits(CODE, unclosed), Err(EditError::MalformedBlock(0)));
+
+        let missing: &str = "<<<<<<< SEARCH\nlet host = 1;\n=======\nlet host = 2;\n>>>>>>> REPLACE";
+        assert_eq!(apply_code_edits(CODE, missing), Err(EditError::SearchNotFound(0)));
+
+        let ambiguous: &str = "<<<<<<< SEARCH\nport\n=======\nhost\n>>>>>>> REPLACE";
+        assert_eq!(apply_code_edits(CODE, ambiguous), Err(EditError::AmbiguousSearch(0)));
+
+        let broken: &str = "<<<<<<< SEARCH\nfn main() {\n=======\nfn main()\n>>>>>>> REPLACE";
+        assert!(matches!(apply_code_edits(CODE, broken), Err(EditError::InvalidResult(_))));
+    }
+}
@@ -0,0 +1,199 @@
+use crossterm::style::Color;
+
+const RUST_KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while",
+];
+
+const ROUTE_ATTRIBUTES: [&str; 5] = ["#[get(", "#[post(", "#[put(", "#[delete(", "#[patch("];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+    Keyword,
+    Text,
+    StringLit,
+    Comment,
+    Attribute,
+}
+
+impl TokenKind {
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Keyword => Color::Magenta,
+            Self::Text => Color::White,
+            Self::StringLit => Color::Green,
+            Self::Comment => Color::DarkGrey,
+            Self::Attribute => Color::Yellow,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+//splits a line of rust into coloured tokens, good enough for reading code in a terminal
+pub fn tokenize_rust_line(line: &str) -> Vec<(TokenKind, String)> {
+    let mut tokens: Vec<(TokenKind, String)> = vec![];
+    let chars: Vec<char> = line.chars().collect();
+    let mut index: usize = 0;
+
+    if line.trim_start().starts_with("#[") {
+        return vec![(TokenKind::Attribute, line.to_string())];
+    }
+
+    while index < chars.len() {
+        let current: char = chars[index];
+
+        if current == '/' && chars.get(index + 1) == Some(&'/') {
+            tokens.push((TokenKind::Comment, chars[index..].iter().collect()));
+            break;
+        }
+
+        if current == '"' {
+            let mut end: usize = index + 1;
+            while end < chars.len() && chars[end] != '"' {
+                if chars[end] == '\\' {
+                    end += 1;
+                }
+                end += 1;
+            }
+            let end: usize = (end + 1).min(chars.len());
+            tokens.push((TokenKind::StringLit, chars[index..end].iter().collect()));
+            index = end;
+            continue;
+        }
+
+        if current.is_alphanumeric() || current == '_' {
+            let mut end: usize = index;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let word: String = chars[index..end].iter().collect();
+            let kind: TokenKind = if RUST_KEYWORDS.contains(&word.as_str()) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Text
+            };
+            tokens.push((kind, word));
+            index = end;
+            continue;
+        }
+
+        match tokens.last_mut() {
+            Some((TokenKind::Text, text)) => text.push(current),
+            _ => tokens.push((TokenKind::Text, current.to_string())),
+        }
+        index += 1;
+    }
+
+    tokens
+}
+
+//line diff based on the longest common subsequence of the two files
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let mut lcs: Vec<Vec<usize>> = vec![vec![0; new_lines.len() + 1]; old_lines.len() + 1];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff: Vec<DiffLine> = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old_lines.len() && j < new_lines.len() {
+        if old_lines[i] == new_lines[j] {
+            diff.push(DiffLine::Same(old_lines[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old_lines[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new_lines[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old_lines[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    diff.extend(new_lines[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+
+    diff
+}
+
+//finds the url paths registered with .route("...") or #[get("...")] style attributes
+pub fn list_route_paths(code: &str) -> Vec<String> {
+    let mut routes: Vec<String> = vec![];
+
+    for line in code.lines() {
+        let trimmed: &str = line.trim();
+        let start: Option<usize> = if let Some(pos) = trimmed.find(".route(\"") {
+            Some(pos + ".route(\"".len())
+        } else {
+            ROUTE_ATTRIBUTES
+                .iter()
+                .find(|attribute| trimmed.starts_with(**attribute))
+                .and_then(|attribute| trimmed[attribute.len()..].find('"').map(|quote| attribute.len() + quote + 1))
+        };
+
+        //an attribute split over several lines has no path on its first line
+        if let Some(route) = start.and_then(|start| trimmed.get(start..)).and_then(|rest| rest.split('"').next()) {
+            routes.push(route.to_string());
+        }
+    }
+
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_tokenize_rust_line() {
+        let tokens: Vec<(TokenKind, String)> =
+            tokenize_rust_line("let url = \"http://x\"; // call it");
+
+        assert_eq!(tokens[0], (TokenKind::Keyword, "let".to_string()));
+        assert_eq!(tokens[3], (TokenKind::StringLit, "\"http://x\"".to_string()));
+        assert_eq!(tokens.last().unwrap(), &(TokenKind::Comment, "// call it".to_string()));
+        assert_eq!(
+            tokens.iter().map(|(_, text)| text.as_str()).collect::<String>(),
+            "let url = \"http://x\"; // call it"
+        );
+    }
+
+    #[test]
+    fn tests_diff_lines() {
+        let diff: Vec<DiffLine> = diff_lines("a\nb\nc", "a\nc\nd");
+
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a".to_string()),
+                DiffLine::Removed("b".to_string()),
+                DiffLine::Same("c".to_string()),
+                DiffLine::Added("d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_list_route_paths() {
+        let code: &str = "App::new()\n  .route(\"/item/{id}\", web::get().to(read_item))\n#[post(\"/signup\")]\nasync fn signup() {}";
+
+        assert_eq!(list_route_paths(code), vec!["/item/{id}", "/signup"]);
+        assert!(list_route_paths("#[get(\n    \"/todos\",\n)]\n#[get(").is_empty());
+    }
+}
@@ -1,12 +1,22 @@
 use crossterm::{
     style::{Color, ResetColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
     ExecutableCommand,
 };
 
-use std::io::{stdin, stdout, Stdout};
-
-#[derive(PartialEq, Debug)]
-
+use crate::apis::call_request::StreamProgress;
+use crate::helpers::code_review::{diff_lines, list_route_paths, tokenize_rust_line, DiffLine};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::models::agents::agent_traits::{FindingSeverity, ReviewFinding};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::{stdin, stdout, Stdout, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PrintCommand {
     AICall,
     UnitTest,
@@ -14,27 +24,39 @@ pub enum PrintCommand {
 }
 
 impl PrintCommand {
+    //the terminal printer subscribed to the event log does the printing
     pub fn print_agent_message(&self, agent_pos: &str, agent_statement: &str) {
-        let mut Stdout: std::io::Stdout = stdout();
-
-        //Decide on the print color
-        let statement_color: Color = match self {
-            Self::AICall => Color::Cyan,
-            Self::UnitTest => Color::Magenta,
-            Self::Issue => Color::Red,
-        };
+        emit(RunEvent::Message {
+            agent: agent_pos.to_string(),
+            kind: self.clone(),
+            message: agent_statement.to_string(),
+        });
+    }
+}
 
-        //print agent statement
-        Stdout.execute(SetForegroundColor(Color::Green)).unwrap();
-        print!("Agent: {}: ", agent_pos);
+//rewrites one line in place while a completion streams in
+pub fn print_stream_progress(agent_operation: &str, progress: &StreamProgress) {
+    let mut stdout: std::io::Stdout = stdout();
 
-        //make selected color
-        Stdout.execute(SetForegroundColor(statement_color)).unwrap();
-        print!("Agent: {}: ", agent_statement);
+    let latest_text: String = progress.latest_text.replace(['\n', '\r'], " ");
+    stdout.execute(Clear(ClearType::CurrentLine)).unwrap();
+    stdout.execute(SetForegroundColor(Color::DarkGrey)).unwrap();
+    print!(
+        "\r{}: {} tokens, {:.1}s {}",
+        agent_operation,
+        progress.tokens_received,
+        progress.elapsed.as_secs_f32(),
+        latest_text
+    );
+    stdout.execute(ResetColor).unwrap();
+    stdout.flush().unwrap();
+}
 
-        //reset color
-        Stdout.execute(ResetColor).unwrap();
-    }
+pub fn finish_stream_progress() {
+    let mut stdout: std::io::Stdout = stdout();
+    stdout.execute(Clear(ClearType::CurrentLine)).unwrap();
+    print!("\r");
+    stdout.flush().unwrap();
 }
 
 //get user request
@@ -60,45 +82,211 @@ pub fn get_user_response(question: &str) -> String {
     return user_response.trim().to_string();
 }
 
-pub fn confirm_safe_code() -> bool {
+#[derive(Debug, PartialEq)]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+    Edited(String),
+    Feedback(String),
+}
+
+pub struct CodeReview<'a> {
+    pub code: &'a str,
+    pub previous_code: &'a str,
+    pub test_code: &'a str,
+    pub external_urls: &'a [String],
+    pub findings: &'a [ReviewFinding],
+}
+
+fn print_highlighted_code(stdout: &mut Stdout, code: &str) {
+    for (line_number, line) in code.lines().enumerate() {
+        stdout.execute(SetForegroundColor(Color::DarkGrey)).unwrap();
+        print!("{:>4} | ", line_number + 1);
+
+        for (kind, text) in tokenize_rust_line(line) {
+            stdout.execute(SetForegroundColor(kind.color())).unwrap();
+            print!("{}", text);
+        }
+        println!();
+    }
+    stdout.execute(ResetColor).unwrap();
+}
+
+fn print_code_diff(stdout: &mut Stdout, previous_code: &str, code: &str) {
+    let diff: Vec<DiffLine> = diff_lines(previous_code, code);
+
+    if diff.iter().all(|line| matches!(line, DiffLine::Same(_))) {
+        println!("no changes since the previous version.");
+        return;
+    }
+
+    //only print changed lines with two lines of context around them
+    let is_changed = |index: usize| !matches!(diff.get(index), Some(DiffLine::Same(_)) | None);
+    for (index, line) in diff.iter().enumerate() {
+        let is_near_change: bool = (index.saturating_sub(2)..=index + 2).any(is_changed);
+        if !is_near_change {
+            continue;
+        }
+
+        match line {
+            DiffLine::Same(text) => {
+                stdout.execute(ResetColor).unwrap();
+                println!("  {}", text);
+            }
+            DiffLine::Added(text) => {
+                stdout.execute(SetForegroundColor(Color::Green)).unwrap();
+                println!("+ {}", text);
+            }
+            DiffLine::Removed(text) => {
+                stdout.execute(SetForegroundColor(Color::Red)).unwrap();
+                println!("- {}", text);
+            }
+        }
+    }
+    stdout.execute(ResetColor).unwrap();
+}
+
+//the integration tests run with cargo test after approval, so they are reviewed too
+fn print_test_code(stdout: &mut Stdout, test_code: &str) {
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    println!();
+    println!("TESTS TO REVIEW:");
+    stdout.execute(ResetColor).unwrap();
+    print_highlighted_code(stdout, test_code);
+}
+
+fn print_review_summary(stdout: &mut Stdout, review: &CodeReview) {
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    println!();
+    println!("ENDPOINTS this code serves:");
+    stdout.execute(ResetColor).unwrap();
+    for route in list_route_paths(review.code) {
+        println!("  {}", route);
+    }
+
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    println!("EXTERNAL URLS this code may call:");
+    stdout.execute(ResetColor).unwrap();
+    for url in review.external_urls {
+        println!("  {}", url);
+    }
+
+    //show what the code reviewer found
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    if review.findings.is_empty() {
+        println!("the code reviewer found no issues.");
+    } else {
+        println!("the code reviewer found the following issues:");
+    }
+
+    for finding in review.findings {
+        let finding_color: Color = match finding.severity {
+            FindingSeverity::High => Color::Red,
+            FindingSeverity::Medium => Color::Yellow,
+            FindingSeverity::Low => Color::Grey,
+        };
+
+        stdout.execute(SetForegroundColor(finding_color)).unwrap();
+        match finding.line {
+            Some(line) => println!(
+                "  [{:?}] {} (line {}): {}",
+                finding.severity, finding.category, line, finding.description
+            ),
+            None => println!(
+                "  [{:?}] {}: {}",
+                finding.severity, finding.category, finding.description
+            ),
+        }
+    }
+    stdout.execute(ResetColor).unwrap();
+}
+
+//opens the code in the user's editor and returns what they saved
+fn edit_code_in_editor(code: &str) -> Option<String> {
+    let editor: String = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let path: PathBuf = env::temp_dir().join("auto_gippity_review_main.rs");
+    fs::write(&path, code).expect("failed to write code for editing");
+
+    let status = Command::new(&editor).arg(&path).status();
+
+    match status {
+        Ok(status) if status.success() => fs::read_to_string(&path).ok(),
+        _ => {
+            println!("editor '{}' did not exit cleanly, keeping the generated code.", editor);
+            None
+        }
+    }
+}
+
+pub fn confirm_safe_code(review: &CodeReview) -> ReviewDecision {
     let mut stdout: std::io::Stdout = stdout();
+
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    println!();
+    println!("CODE TO REVIEW:");
+    stdout.execute(ResetColor).unwrap();
+    print_highlighted_code(&mut stdout, review.code);
+
+    stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
+    println!();
+    println!("CHANGES SINCE THE PREVIOUS VERSION:");
+    stdout.execute(ResetColor).unwrap();
+    print_code_diff(&mut stdout, review.previous_code, review.code);
+
+    print_test_code(&mut stdout, review.test_code);
+    print_review_summary(&mut stdout, review);
+
     loop {
         stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-        println!("");
+        println!();
         println!("WARNING ! You are about to run code written entirely by AI. ");
         println!("it is recommended to review the code first and continue. ");
 
-        stdout.execute(ResetColor).unwrap();
-
         stdout.execute(SetForegroundColor(Color::Green)).unwrap();
         println!("[1] all good");
 
-
         stdout.execute(SetForegroundColor(Color::DarkRed)).unwrap();
         println!("[2] let's stop this project");
 
-        stdout.execute(ResetColor).unwrap();
+        stdout.execute(SetForegroundColor(Color::Yellow)).unwrap();
+        println!("[3] edit the code in $EDITOR");
+        println!("[4] send feedback to the agent");
 
+        stdout.execute(SetForegroundColor(Color::Grey)).unwrap();
+        println!("[5] show the code again");
+
+        stdout.execute(ResetColor).unwrap();
 
         let mut human_response: String = String::new();
 
         stdin().read_line(&mut human_response).expect("Failed to read response.");
 
-
         let human_response: String = human_response.trim().to_lowercase();
 
-
         match human_response.as_str() {
-            "1" | "ok" | "y" => return true,
-            "2" | "no" | "n" => return false,
+            "1" | "ok" | "y" => return ReviewDecision::Approve,
+            "2" | "no" | "n" => return ReviewDecision::Reject,
+            "3" | "edit" => {
+                if let Some(edited_code) = edit_code_in_editor(review.code) {
+                    return ReviewDecision::Edited(edited_code);
+                }
+            }
+            "4" | "feedback" => {
+                let feedback: String = get_user_response("what should the agent change?");
+                if !feedback.is_empty() {
+                    return ReviewDecision::Feedback(feedback);
+                }
+            }
+            "5" | "show" => {
+                print_highlighted_code(&mut stdout, review.code);
+                print_test_code(&mut stdout, review.test_code);
+                print_review_summary(&mut stdout, review);
+            }
             _ => {
-                println!("invalid input. please select '1' or '2'")
+                println!("invalid input. please select '1' to '5'")
             }
         }
-
-
-
-    }   
+    }
 }
 
 
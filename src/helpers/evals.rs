@@ -0,0 +1,495 @@
+use crate::ai_functions::{find_ai_function, AiFunction};
+use crate::apis::call_request::call_gpt;
+use crate::helpers::general::extend_ai_function_with;
+use crate::helpers::prompts::PromptRegistry;
+use crate::helpers::run_report::{escape_html, REPORT_STYLE};
+use crate::models::general::llm::Message;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+pub const EVALS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/evals");
+
+//files inside the eval directory
+pub const EVAL_RESULTS_FILE: &str = "eval_results.json";
+
+pub const EVAL_REPORT_FILE: &str = "eval_report.html";
+
+//one golden case, expected is compared field by field with the decoded response
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EvalCase {
+    #[serde(default)]
+    pub name: String,
+    pub function: String,
+    pub input: String,
+    pub expected: Option<Value>,
+    #[serde(default)]
+    pub must_contain: Vec<String>,
+    //what the mock provider answers
+    pub mock_response: Option<String>,
+}
+
+//every *.toml file is one case, named after the file unless it says otherwise
+pub fn load_cases(dir: &Path) -> Result<Vec<EvalCase>, Box<dyn std::error::Error>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().and_then(|extension| extension.to_str()) == Some("toml"));
+    paths.sort();
+
+    let mut cases: Vec<EvalCase> = vec![];
+    for path in paths {
+        let mut case: EvalCase = toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| format!("invalid eval case {}: {}", path.display(), e))?;
+
+        if case.name.is_empty() {
+            case.name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+        if find_ai_function(&case.function).is_none() {
+            return Err(format!("eval case {} uses unknown ai_function {}", case.name, case.function).into());
+        }
+        cases.push(case);
+    }
+
+    Ok(cases)
+}
+
+#[async_trait]
+pub trait EvalProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn complete(
+        &self,
+        case: &EvalCase,
+        messages: Vec<Message>,
+    ) -> Result<String, Box<dyn std::error::Error + Send>>;
+}
+
+//the model the agents use, answers go through the cache and rate limiter like any other call
+pub struct OpenAiProvider;
+
+#[async_trait]
+impl EvalProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn complete(
+        &self,
+        _case: &EvalCase,
+        messages: Vec<Message>,
+    ) -> Result<String, Box<dyn std::error::Error + Send>> {
+        call_gpt("Eval", messages, false, &mut |_| {}).await
+    }
+}
+
+//answers with the case's mock_response, for checking cases and scoring without a model
+pub struct MockProvider;
+
+#[async_trait]
+impl EvalProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn complete(
+        &self,
+        case: &EvalCase,
+        _messages: Vec<Message>,
+    ) -> Result<String, Box<dyn std::error::Error + Send>> {
+        case.mock_response.clone().ok_or_else(|| -> Box<dyn std::error::Error + Send> {
+            Box::new(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("eval case {} has no mock_response", case.name),
+            ))
+        })
+    }
+}
+
+pub fn provider_by_name(name: &str) -> Option<Box<dyn EvalProvider>> {
+    match name {
+        "openai" => Some(Box::new(OpenAiProvider)),
+        "mock" => Some(Box::new(MockProvider)),
+        _ => None,
+    }
+}
+
+//the structured scores are only set for cases with an expected output
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaseScore {
+    pub is_json_valid: Option<bool>,
+    pub is_exact_match: Option<bool>,
+    pub field_accuracy: Option<f64>,
+    pub failed_assertions: Vec<String>,
+}
+
+impl CaseScore {
+    pub fn passed(&self) -> bool {
+        self.failed_assertions.is_empty() && self.field_accuracy.is_none_or(|accuracy| accuracy >= 1.0)
+    }
+}
+
+//objects score the share of matching fields, arrays the share of expected items found, extra output is not penalised
+pub fn field_accuracy(expected: &Value, actual: &Value) -> f64 {
+    match (expected, actual) {
+        (Value::Object(expected_fields), Value::Object(actual_fields)) if !expected_fields.is_empty() => {
+            let total: f64 = expected_fields
+                .iter()
+                .map(|(key, expected_value)| {
+                    actual_fields
+                        .get(key)
+                        .map_or(0.0, |actual_value| field_accuracy(expected_value, actual_value))
+                })
+                .sum();
+            total / expected_fields.len() as f64
+        }
+        (Value::Array(expected_items), Value::Array(actual_items)) if !expected_items.is_empty() => {
+            let total: f64 = expected_items
+                .iter()
+                .map(|expected_item| {
+                    actual_items
+                        .iter()
+                        .map(|actual_item| field_accuracy(expected_item, actual_item))
+                        .fold(0.0, f64::max)
+                })
+                .sum();
+            total / expected_items.len() as f64
+        }
+        _ if expected == actual => 1.0,
+        _ => 0.0,
+    }
+}
+
+pub fn score_response(case: &EvalCase, response: &str) -> CaseScore {
+    let failed_assertions: Vec<String> = case
+        .must_contain
+        .iter()
+        .filter(|text| !response.contains(text.as_str()))
+        .map(|text| format!("missing \"{}\"", text))
+        .collect();
+
+    let Some(expected) = &case.expected else {
+        return CaseScore {
+            is_json_valid: None,
+            is_exact_match: None,
+            field_accuracy: None,
+            failed_assertions,
+        };
+    };
+
+    match serde_json::from_str::<Value>(response.trim()) {
+        Ok(actual) => CaseScore {
+            is_json_valid: Some(true),
+            is_exact_match: Some(actual == *expected),
+            field_accuracy: Some(field_accuracy(expected, &actual)),
+            failed_assertions,
+        },
+        Err(_) => CaseScore {
+            is_json_valid: Some(false),
+            is_exact_match: Some(false),
+            field_accuracy: Some(0.0),
+            failed_assertions,
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaseResult {
+    pub case: String,
+    pub function: String,
+    pub prompt_version: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub score: Option<CaseScore>,
+    pub duration_ms: u64,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.score.as_ref().is_some_and(|score| score.passed())
+    }
+}
+
+//all cases run with one set of prompts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvalRun {
+    pub prompts: String,
+    pub provider: String,
+    pub results: Vec<CaseResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalSummary {
+    pub cases: usize,
+    pub passed: usize,
+    pub json_valid_rate: Option<f64>,
+    pub exact_match_rate: Option<f64>,
+    pub mean_field_accuracy: Option<f64>,
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+//failed calls count as zero for every structured score
+fn structured_scores(results: &[CaseResult], score_of: impl Fn(&CaseScore) -> Option<f64>) -> Option<f64> {
+    let values: Vec<f64> = results
+        .iter()
+        .filter_map(|result| match &result.score {
+            Some(score) => score_of(score),
+            None => Some(0.0),
+        })
+        .collect();
+    mean(&values)
+}
+
+impl EvalRun {
+    pub fn summary(&self) -> EvalSummary {
+        let is_structured = |result: &&CaseResult| result.score.as_ref().is_none_or(|score| score.field_accuracy.is_some());
+        let structured: Vec<CaseResult> = self.results.iter().filter(is_structured).cloned().collect();
+        let as_rate = |flag: Option<bool>| flag.map(|flag| if flag { 1.0 } else { 0.0 });
+
+        EvalSummary {
+            cases: self.results.len(),
+            passed: self.results.iter().filter(|result| result.passed()).count(),
+            json_valid_rate: structured_scores(&structured, |score| as_rate(score.is_json_valid)),
+            exact_match_rate: structured_scores(&structured, |score| as_rate(score.is_exact_match)),
+            mean_field_accuracy: structured_scores(&structured, |score| score.field_accuracy),
+        }
+    }
+}
+
+pub async fn run_evals(
+    cases: &[EvalCase],
+    prompts_label: &str,
+    registry: &PromptRegistry,
+    provider: &dyn EvalProvider,
+) -> EvalRun {
+    let mut results: Vec<CaseResult> = vec![];
+
+    for case in cases {
+        let ai_func: AiFunction =
+            find_ai_function(&case.function).expect("eval case uses an unknown ai_function");
+        let messages: Vec<Message> = extend_ai_function_with(registry, ai_func, &case.input);
+
+        let started: Instant = Instant::now();
+        let response = provider.complete(case, messages).await;
+        let duration_ms: u64 = started.elapsed().as_millis() as u64;
+
+        results.push(CaseResult {
+            case: case.name.clone(),
+            function: case.function.clone(),
+            prompt_version: registry.version_of(ai_func(&case.input)).version,
+            score: response.as_ref().ok().map(|response| score_response(case, response)),
+            error: response.as_ref().err().map(|e| e.to_string()),
+            response: response.ok(),
+            duration_ms,
+        });
+    }
+
+    EvalRun {
+        prompts: prompts_label.to_string(),
+        provider: provider.name().to_string(),
+        results,
+    }
+}
+
+fn percent_cell(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("<td>{:.0}%</td>", value * 100.0),
+        None => "<td>-</td>".to_string(),
+    }
+}
+
+fn result_cell(result: Option<&CaseResult>) -> String {
+    let Some(result) = result else {
+        return "<td>-</td>".to_string();
+    };
+
+    let accuracy: String = result
+        .score
+        .as_ref()
+        .and_then(|score| score.field_accuracy)
+        .map(|accuracy| format!(", {:.0}% fields", accuracy * 100.0))
+        .unwrap_or_default();
+    let problems: Vec<String> = match &result.score {
+        Some(score) => score.failed_assertions.clone(),
+        None => result.error.iter().cloned().collect(),
+    };
+    let class: &str = if result.passed() { "ok" } else { "failed" };
+
+    format!(
+        "<td class=\"{}\" title=\"{}\">v{}: {}{}</td>",
+        class,
+        escape_html(&problems.join("; ")),
+        escape_html(&result.prompt_version),
+        if result.passed() { "passed" } else { "failed" },
+        accuracy
+    )
+}
+
+//one column per set of prompts so a change shows up case by case
+pub fn render_eval_report(runs: &[EvalRun]) -> String {
+    let mut html: String = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Prompt evaluation</title>\n<style>{}</style>\n</head>\n<body>\n<h1>Prompt evaluation</h1>\n",
+        REPORT_STYLE
+    );
+
+    html.push_str("<h2>Summary</h2>\n<table>\n<tr><th>Prompts</th><th>Provider</th><th>Passed</th><th>Valid JSON</th><th>Exact match</th><th>Field accuracy</th></tr>\n");
+    for run in runs {
+        let summary: EvalSummary = run.summary();
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{} / {}</td>{}{}{}</tr>\n",
+            escape_html(&run.prompts),
+            escape_html(&run.provider),
+            summary.passed,
+            summary.cases,
+            percent_cell(summary.json_valid_rate),
+            percent_cell(summary.exact_match_rate),
+            percent_cell(summary.mean_field_accuracy)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Cases</h2>\n<table>\n<tr><th>Case</th><th>Function</th>");
+    for run in runs {
+        html.push_str(&format!("<th>{}</th>", escape_html(&run.prompts)));
+    }
+    html.push_str("</tr>\n");
+
+    let Some(first_run) = runs.first() else {
+        html.push_str("</table>\n</body>\n</html>\n");
+        return html;
+    };
+    for case_result in &first_run.results {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td>",
+            escape_html(&case_result.case),
+            escape_html(&case_result.function)
+        ));
+        for run in runs {
+            let result: Option<&CaseResult> = run.results.iter().find(|result| result.case == case_result.case);
+            html.push_str(&result_cell(result));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+
+    html
+}
+
+pub fn write_eval_report(eval_dir: &Path, runs: &[EvalRun]) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    fs::create_dir_all(eval_dir)?;
+    fs::write(eval_dir.join(EVAL_RESULTS_FILE), serde_json::to_string_pretty(runs)?)?;
+
+    let report_path: PathBuf = eval_dir.join(EVAL_REPORT_FILE);
+    fs::write(&report_path, render_eval_report(runs))?;
+    Ok(report_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scope_case(mock_response: &str) -> EvalCase {
+        EvalCase {
+            name: "todo_app".to_string(),
+            function: "print_project_scope".to_string(),
+            input: "I need a simple TODO app".to_string(),
+            expected: Some(json!({
+                "is_crud_required": true,
+                "is_user_login_and_logout": false,
+                "is_external_urls_required": false,
+                "is_database_required": true
+            })),
+            must_contain: vec![],
+            mock_response: Some(mock_response.to_string()),
+        }
+    }
+
+    #[test]
+    fn tests_scoring_responses() {
+        let case: EvalCase = scope_case("");
+
+        let exact: CaseScore = score_response(&case, &case.expected.as_ref().unwrap().to_string());
+        assert_eq!(exact.is_exact_match, Some(true));
+        assert!(exact.passed());
+
+        let half: CaseScore = score_response(
+            &case,
+            r#"{"is_crud_required": true, "is_user_login_and_logout": true, "is_database_required": true}"#,
+        );
+        assert_eq!(half.field_accuracy, Some(0.5));
+        assert!(!half.passed());
+
+        let prose: CaseScore = score_response(&case, "Sure! Here is the scope");
+        assert_eq!(prose.is_json_valid, Some(false));
+
+        //extra items in the output do not count against it
+        let urls: Value = json!(["https://api.binance.com/api/v3/exchangeInfo"]);
+        let actual: Value = json!(["https://api.kraken.com/0/public/Time", "https://api.binance.com/api/v3/exchangeInfo"]);
+        assert_eq!(field_accuracy(&urls, &actual), 1.0);
+
+        let text_case: EvalCase = EvalCase {
+            expected: None,
+            must_contain: vec!["HttpServer".to_string()],
+            ..scope_case("")
+        };
+        assert_eq!(score_response(&text_case, "fn main() {}").failed_assertions, vec!["missing \"HttpServer\""]);
+    }
+
+    #[tokio::test]
+    async fn tests_run_evals_with_mock_provider() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("auto_gippity_evals_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("print_project_scope.toml"),
+            "version = \"2\"\ntemplate = \"Decide the scope of the website\"\n",
+        )
+        .unwrap();
+
+        let cases: Vec<EvalCase> = vec![
+            scope_case(r#"{"is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false, "is_database_required": true}"#),
+            EvalCase {
+                name: "no_mock".to_string(),
+                mock_response: None,
+                ..scope_case("")
+            },
+        ];
+
+        let builtin: EvalRun = run_evals(&cases, "builtin", &PromptRegistry::default(), &MockProvider).await;
+        let overridden: EvalRun =
+            run_evals(&cases, "v2", &PromptRegistry::load(&dir).unwrap(), &MockProvider).await;
+
+        assert_eq!(builtin.results[0].prompt_version, "builtin");
+        assert_eq!(overridden.results[0].prompt_version, "2");
+        assert!(builtin.results[0].passed());
+        assert!(builtin.results[1].error.as_ref().unwrap().contains("no mock_response"));
+
+        let summary: EvalSummary = builtin.summary();
+        assert_eq!(summary.passed, 1);
+        assert_eq!(summary.mean_field_accuracy, Some(0.5));
+
+        let report_path: PathBuf = write_eval_report(&dir, &[builtin, overridden]).unwrap();
+        let report: String = fs::read_to_string(report_path).unwrap();
+        assert!(report.contains("<th>v2</th>"));
+        assert!(dir.join(EVAL_RESULTS_FILE).exists());
+
+        //the bundled cases all pass against their own mock responses
+        let bundled: Vec<EvalCase> = load_cases(Path::new(EVALS_PATH)).unwrap();
+        let bundled_run: EvalRun = run_evals(&bundled, "builtin", &PromptRegistry::default(), &MockProvider).await;
+        assert!(bundled_run.results.iter().all(|result| result.passed()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use crossterm::{
+    style::{Color, ResetColor, SetForegroundColor},
+    ExecutableCommand,
+};
+
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::llm::Message;
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//file inside the run directory
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    AgentStarted {
+        agent: String,
+    },
+    AgentFinished {
+        agent: String,
+        succeeded: bool,
+        error: Option<String>,
+    },
+    StateTransition {
+        agent: String,
+        from: String,
+        to: String,
+    },
+    Message {
+        agent: String,
+        kind: PrintCommand,
+        message: String,
+    },
+    LlmRequest {
+        agent: String,
+        operation: String,
+        #[serde(default)]
+        prompt: String,
+        #[serde(default)]
+        prompt_version: String,
+        #[serde(default)]
+        printer_version: String,
+        messages: Vec<Message>,
+    },
+    PromptInjection {
+        agent: String,
+        operation: String,
+        matches: Vec<String>,
+    },
+    ToolCall {
+        agent: String,
+        tool: String,
+        arguments: String,
+        output: Option<String>,
+        error: Option<String>,
+        duration_ms: u64,
+    },
+    AgentStep {
+        agent: String,
+        step: usize,
+        thought: String,
+        tools: Vec<String>,
+        tokens_used: u32,
+    },
+    LlmResponse {
+        agent: String,
+        operation: String,
+        response: Option<String>,
+        error: Option<String>,
+        duration_ms: u64,
+    },
+    Build {
+        command: String,
+        succeeded: bool,
+        output: String,
+        duration_ms: u64,
+    },
+    EndpointResult {
+        route: String,
+        status: Option<u16>,
+        error: Option<String>,
+    },
+    UrlCheck {
+        url: String,
+        status: Option<u16>,
+        error: Option<String>,
+    },
+    LlmCache {
+        key: String,
+        hit: bool,
+    },
+    LlmQueued {
+        agent: String,
+        wait_ms: u64,
+    },
+    LlmRetry {
+        attempt: u32,
+        error: String,
+        delay_ms: u64,
+    },
+    TokenUsage {
+        model: String,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        total_tokens: u32,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EventRecord {
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: RunEvent,
+}
+
+pub trait EventSubscriber: Send + Sync {
+    fn on_event(&self, record: &EventRecord);
+}
+
+//prints agent messages in colour, everything else only goes to the other subscribers
+pub struct TerminalPrinter;
+
+impl EventSubscriber for TerminalPrinter {
+    fn on_event(&self, record: &EventRecord) {
+        if let RunEvent::Message { agent, kind, message } = &record.event {
+            let mut stdout: std::io::Stdout = stdout();
+
+            //Decide on the print color
+            let statement_color: Color = match kind {
+                PrintCommand::AICall => Color::Cyan,
+                PrintCommand::UnitTest => Color::Magenta,
+                PrintCommand::Issue => Color::Red,
+            };
+
+            //print agent statement
+            stdout.execute(SetForegroundColor(Color::Green)).unwrap();
+            print!("Agent: {}: ", agent);
+
+            //make selected color
+            stdout.execute(SetForegroundColor(statement_color)).unwrap();
+            println!("{}", message);
+
+            //reset color
+            stdout.execute(ResetColor).unwrap();
+        }
+    }
+}
+
+//appends every event as one JSON line so a run can be replayed afterwards
+pub struct JsonlWriter {
+    file: Mutex<File>,
+    has_failed: AtomicBool,
+}
+
+impl JsonlWriter {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            has_failed: AtomicBool::new(false),
+        })
+    }
+}
+
+impl EventSubscriber for JsonlWriter {
+    //a lost event must not end the run, the first failure is reported and the rest dropped quietly
+    fn on_event(&self, record: &EventRecord) {
+        let written: Result<(), String> = serde_json::to_string(record)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                writeln!(file, "{}", line).map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = written {
+            if !self.has_failed.swap(true, Ordering::Relaxed) {
+                tracing::warn!(error = %e, "failed to write run event log, dropping events");
+            }
+        }
+    }
+}
+
+static SUBSCRIBERS: LazyLock<RwLock<Vec<Box<dyn EventSubscriber>>>> =
+    LazyLock::new(|| RwLock::new(vec![Box::new(TerminalPrinter)]));
+
+pub fn subscribe(subscriber: Box<dyn EventSubscriber>) {
+    SUBSCRIBERS.write().unwrap().push(subscriber);
+}
+
+pub fn emit(event: RunEvent) {
+    let timestamp_ms: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0);
+
+    let record: EventRecord = EventRecord { timestamp_ms, event };
+    for subscriber in SUBSCRIBERS.read().unwrap().iter() {
+        subscriber.on_event(&record);
+    }
+}
+
+//lines which fail to decode are skipped so a truncated transcript still loads
+pub fn load_events(path: &Path) -> Vec<EventRecord> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+//starts writing the run transcript into the run directory
+pub fn start_event_log(run_dir: &Path) -> std::io::Result<PathBuf> {
+    let path: PathBuf = run_dir.join(EVENTS_FILE);
+    subscribe(Box::new(JsonlWriter::create(&path)?));
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn tests_events_written_as_json_lines() {
+        let path: PathBuf = env::temp_dir().join(format!("auto_gippity_events_{}.jsonl", std::process::id()));
+        let writer: JsonlWriter = JsonlWriter::create(&path).unwrap();
+
+        let records: Vec<EventRecord> = vec![
+            EventRecord {
+                timestamp_ms: 1,
+                event: RunEvent::Message {
+                    agent: "Backend Developer".to_string(),
+                    kind: PrintCommand::UnitTest,
+                    message: "Building project".to_string(),
+                },
+            },
+            EventRecord {
+                timestamp_ms: 2,
+                event: RunEvent::EndpointResult {
+                    route: "/health".to_string(),
+                    status: Some(200),
+                    error: None,
+                },
+            },
+        ];
+
+        for record in &records {
+            writer.on_event(record);
+        }
+
+        let contents: String = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(r#""event":"message""#));
+
+        assert_eq!(load_events(&path), records);
+
+        //a file that cannot be written drops the event instead of panicking
+        let read_only: JsonlWriter = JsonlWriter {
+            file: Mutex::new(File::open(&path).unwrap()),
+            has_failed: AtomicBool::new(false),
+        };
+        read_only.on_event(&records[0]);
+        read_only.on_event(&records[1]);
+        assert!(read_only.has_failed.load(Ordering::Relaxed));
+        assert_eq!(load_events(&path), records);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
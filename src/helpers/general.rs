@@ -1,36 +1,67 @@
-use crate::apis::call_request::call_gpt;
-use crate::helpers::command_line::PrintCommand;
+use crate::apis::call_request::{call_gpt, LlmError};
+use crate::apis::llm_cache::is_cached_function;
+use crate::apis::tool_calling::{run_tool_loop, OpenAiChat, ToolLoopOutcome, ToolSet};
+use crate::helpers::command_line::{finish_stream_progress, print_stream_progress, PrintCommand};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::helpers::prompt_guard::{delimit_input, detect_prompt_injection};
+use crate::helpers::prompts::{PromptRegistry, PromptVersion, PROMPTS};
+use crate::helpers::templates::{CodeTemplate, TemplateRegistry, DEFAULT_TEMPLATE};
+use crate::models::agents::agent_traits::{FactSheet, Revision};
 use crate::models::general::llm::Message;
 use reqwest::{Client, Response};
 use serde::de::DeserializeOwned;
+use crate::helpers::scaffold::{project_dir, run_dir};
 use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::instrument;
 
-pub const CODE_TEMPLATE_PATH: &str = "/Users/chira/Desktop/auto-gpt/web_templet/src/code_template.rs";
+//files inside the generated project
+pub const EXEC_MAIN_FILE: &str = "src/main.rs";
 
-pub const WEB_SERVER_PROJECT_PATH: &str = "/Users/chira/Desktop/auto-gpt/web_templet/";
+pub const EXEC_TESTS_FILE: &str = "tests/api_tests.rs";
 
-pub const EXEC_MAIN_PATH: &str = "/Users/chira/Desktop/auto-gpt/web_templet/src/main.rs";
+//files inside the run directory
+pub const API_SCHEMA_FILE: &str = "api_schema.json";
 
-pub const API_SCHEMA_PATH: &str = "/Users/chira/Desktop/auto-gpt/auto_gippity/schemas/api_schema.json";
+pub const FACTSHEET_FILE: &str = "factsheet.json";
 
+pub const REVISIONS_FILE: &str = "revisions.json";
 
+//rounds of tool calls before an ai_function has to answer
+pub const MAX_TOOL_ROUNDS: usize = 12;
 
-pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -> Message {
-    let ai_function_str: &str = ai_func(func_input);
 
-    
 
-    let msg: String = format!("FUNCTION: {} INSTRUCTION: you are a function priter.you ONLY print the result of functions.
-    Nothing else.No commentery.Here is the input to the function: {}.
-    Print out what the function will return.",
-    ai_function_str, func_input);
+//the prompt comes from the prompt registry, which falls back to the ai_function's doc comments
+pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -> Vec<Message> {
+    extend_ai_function_with(&PROMPTS, ai_func, func_input)
+}
 
-    
+pub fn extend_ai_function_with(
+    registry: &PromptRegistry,
+    ai_func: fn(&str) -> &'static str,
+    func_input: &str,
+) -> Vec<Message> {
+    extend_function_spec(registry, ai_func(func_input), func_input)
+}
 
-    Message {
-        role: "system".to_string(),
-        content: msg,
+//the function spec is the system message and the input a delimited user message, after any few-shot examples
+pub fn extend_function_spec(registry: &PromptRegistry, ai_function_str: &str, func_input: &str) -> Vec<Message> {
+    let message = |role: &str, content: String| Message {
+        role: role.to_string(),
+        content,
+        ..Default::default()
+    };
+
+    let mut messages: Vec<Message> = vec![message("system", registry.render_ai_function(ai_function_str))];
+    for example in registry.examples(ai_function_str) {
+        messages.push(message("user", delimit_input(&example.input)));
+        messages.push(message("assistant", example.output.clone()));
     }
+    messages.push(message("user", delimit_input(func_input)));
+
+    messages
 }
 
 
@@ -40,23 +71,160 @@ pub async fn ai_task_request(
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> String {
+) -> Result<String, Box<dyn std::error::Error>> {
+    ai_spec_request(msg_context, agent_position, agent_operation, function_pass("")).await
+}
+
+//Ctrl-C comes back as an error so the manager still saves the run, any other failure stops it
+fn cancelled_or_panic(e: Box<dyn std::error::Error + Send>) -> Box<dyn std::error::Error> {
+    match e.downcast_ref::<LlmError>() {
+        Some(LlmError::Cancelled) => Box::new(LlmError::Cancelled),
+        _ => panic!("failed to call OpenAI: {}", e),
+    }
+}
+
+//for function specs built at runtime, like the ones of declarative agents
+#[instrument(skip_all, fields(agent = agent_position, operation = agent_operation))]
+pub async fn ai_spec_request(
+    msg_context: String,
+    agent_position: &str,
+    agent_operation: &str,
+    ai_function_str: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
     
-    let extended_msg: Message = extend_ai_function(function_pass, &msg_context);
+    let extended_msgs: Vec<Message> = extend_function_spec(&PROMPTS, ai_function_str, &msg_context);
+    let prompt_version: PromptVersion = PROMPTS.version_of(ai_function_str);
 
     
     PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
 
     
-    let llm_respons_res = call_gpt(vec![extended_msg.clone()]).await;
+    let llm_respons_res = logged_call_gpt(agent_position, agent_operation, &prompt_version, extended_msgs).await;
 
     
-    match llm_respons_res {
-        Ok(llm_resp) => llm_resp,
-        Err(_) => call_gpt(vec![extended_msg.clone()])
-            .await
-            .expect("failed twice to connect OpenAI"),
+    //call_gpt already retried whatever was worth retrying
+    llm_respons_res.map_err(cancelled_or_panic)
+}
+
+//for text a person typed, generated code and artefacts are not checked
+//the input is still sent, delimited as data, but the attempt goes on record
+pub fn guard_input(msg_context: &str, agent_position: &str, agent_operation: &str) -> Vec<String> {
+    let injection_matches: Vec<String> = detect_prompt_injection(msg_context);
+    if injection_matches.is_empty() {
+        return injection_matches;
     }
+
+    PrintCommand::Issue.print_agent_message(
+        agent_position,
+        &format!("input looks like it tries to override the instructions: {}", injection_matches.join(", ")),
+    );
+    emit(RunEvent::PromptInjection {
+        agent: agent_position.to_string(),
+        operation: agent_operation.to_string(),
+        matches: injection_matches.clone(),
+    });
+    injection_matches
+}
+
+//like ai_task_request, but the model may call the given tools before it prints the result
+pub async fn ai_task_request_with_tools(
+    msg_context: String,
+    agent_position: &str,
+    agent_operation: &str,
+    function_pass: for<'a> fn(&'a str) -> &'static str,
+    tools: &ToolSet,
+) -> Result<String, Box<dyn std::error::Error>> {
+    ai_spec_request_with_tools(msg_context, agent_position, agent_operation, function_pass(""), tools).await
+}
+
+#[instrument(skip_all, fields(agent = agent_position, operation = agent_operation))]
+pub async fn ai_spec_request_with_tools(
+    msg_context: String,
+    agent_position: &str,
+    agent_operation: &str,
+    ai_function_str: &str,
+    tools: &ToolSet,
+) -> Result<String, Box<dyn std::error::Error>> {
+    //without any granted tools it is a plain request, which may be answered from the cache
+    if tools.definitions().is_empty() {
+        return ai_spec_request(msg_context, agent_position, agent_operation, ai_function_str).await;
+    }
+
+    let extended_msgs: Vec<Message> = extend_function_spec(&PROMPTS, ai_function_str, &msg_context);
+    let prompt_version: PromptVersion = PROMPTS.version_of(ai_function_str);
+
+    PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
+
+    emit(RunEvent::LlmRequest {
+        agent: agent_position.to_string(),
+        operation: agent_operation.to_string(),
+        prompt: prompt_version.name.clone(),
+        prompt_version: prompt_version.version.clone(),
+        printer_version: prompt_version.printer_version.clone(),
+        messages: extended_msgs.clone(),
+    });
+
+    let started: Instant = Instant::now();
+    let outcome = run_tool_loop(&OpenAiChat, agent_position, extended_msgs, tools, MAX_TOOL_ROUNDS).await;
+
+    emit(RunEvent::LlmResponse {
+        agent: agent_position.to_string(),
+        operation: agent_operation.to_string(),
+        response: outcome.as_ref().ok().map(|outcome| outcome.content.clone()),
+        error: outcome.as_ref().err().map(|e| e.to_string()),
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+
+    match outcome {
+        Ok(ToolLoopOutcome {
+            content,
+            is_finished: true,
+            ..
+        }) => Ok(content),
+        //whatever the tools changed stays, the caller decides what to make of it
+        Ok(ToolLoopOutcome { content, rounds, .. }) => {
+            let issue_msg: String = format!("stopped after {} rounds of tool calls", rounds);
+            PrintCommand::Issue.print_agent_message(agent_position, issue_msg.as_str());
+            Ok(content)
+        }
+        Err(e) => Err(cancelled_or_panic(e)),
+    }
+}
+
+//records the request, the response and how long it took in the run transcript
+#[instrument(name = "call_gpt", skip_all)]
+async fn logged_call_gpt(
+    agent_position: &str,
+    agent_operation: &str,
+    prompt_version: &PromptVersion,
+    messages: Vec<Message>,
+) -> Result<String, Box<dyn std::error::Error + Send>> {
+    emit(RunEvent::LlmRequest {
+        agent: agent_position.to_string(),
+        operation: agent_operation.to_string(),
+        prompt: prompt_version.name.clone(),
+        prompt_version: prompt_version.version.clone(),
+        printer_version: prompt_version.printer_version.clone(),
+        messages: messages.clone(),
+    });
+
+    let started: Instant = Instant::now();
+    let is_cacheable: bool = is_cached_function(&prompt_version.name);
+    let llm_response = call_gpt(agent_position, messages, is_cacheable, &mut |progress| {
+        print_stream_progress(agent_operation, progress)
+    })
+    .await;
+    finish_stream_progress();
+
+    emit(RunEvent::LlmResponse {
+        agent: agent_position.to_string(),
+        operation: agent_operation.to_string(),
+        response: llm_response.as_ref().ok().cloned(),
+        error: llm_response.as_ref().err().map(|e| e.to_string()),
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+
+    llm_response
 }
 
 
@@ -66,14 +234,14 @@ pub async fn ai_task_request_decoded<T: DeserializeOwned>(
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> T {
+) -> Result<T, Box<dyn std::error::Error>> {
     let llm_response: String =
-        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await;
+        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await?;
 
     let decoded_response: T = serde_json::from_str(&llm_response.as_str())
         .expect("failed to decode ai response from json");
 
-    return decoded_response;
+    Ok(decoded_response)
 }
 
 
@@ -83,28 +251,79 @@ pub async fn check_status_code(client: &Client, url: &str) -> Result<u16, reqwes
 }
 
 
-pub fn read_code_template_contents() -> String {
-    let path: String = String::from(CODE_TEMPLATE_PATH);
-    fs::read_to_string(path).expect("failed to read file contents!")
+pub fn read_code_template_contents(factsheet: &FactSheet) -> String {
+    active_code_template(factsheet).read_code()
+}
+
+//the template chosen by the architect, or the default one for older fact sheets
+pub fn active_code_template(factsheet: &FactSheet) -> CodeTemplate {
+    let registry: TemplateRegistry = TemplateRegistry::load_default();
+    let code_template: Option<String> = factsheet.code_template();
+    let name: &str = code_template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+
+    registry
+        .get(name)
+        .or_else(|| registry.get(DEFAULT_TEMPLATE))
+        .expect("code template not found")
+        .clone()
 }
 
 pub fn read_exec_main_contents() -> String {
-    let path: String = String::from(CODE_TEMPLATE_PATH);
+    let path: PathBuf = project_dir().join(EXEC_MAIN_FILE);
     fs::read_to_string(path).expect("failed to read file contents!")
 }
 
 
 pub fn save_backend_code(contents: &String) {
-    let path: String = String::from(EXEC_MAIN_PATH);
+    let path: PathBuf = project_dir().join(EXEC_MAIN_FILE);
     fs::write(path, contents).expect("failed to write main.rs file");
 }
 
 
+pub fn save_backend_tests(contents: &String) {
+    let path: PathBuf = project_dir().join(EXEC_TESTS_FILE);
+    if let Some(tests_dir) = path.parent() {
+        fs::create_dir_all(tests_dir).expect("failed to create tests directory");
+    }
+    fs::write(path, contents).expect("failed to write integration tests file");
+}
+
+
 pub fn save_api_endpoints(api_endpoints: &String) {
-    let path: String = String::from(API_SCHEMA_PATH);
+    let path: PathBuf = run_dir().join(API_SCHEMA_FILE);
     fs::write(path, api_endpoints).expect("failed to write api endpoints to file");
 }
 
+pub fn save_factsheet(factsheet: &FactSheet) {
+    let factsheet_str: String =
+        serde_json::to_string_pretty(factsheet).expect("failed to encode factsheet");
+    fs::write(run_dir().join(FACTSHEET_FILE), factsheet_str).expect("failed to write factsheet to file");
+}
+
+pub fn load_factsheet() -> FactSheet {
+    let factsheet_str: String =
+        fs::read_to_string(run_dir().join(FACTSHEET_FILE)).expect("failed to read factsheet file");
+    serde_json::from_str(&factsheet_str).expect("failed to decode factsheet")
+}
+
+pub fn load_revisions() -> Vec<Revision> {
+    match fs::read_to_string(run_dir().join(REVISIONS_FILE)) {
+        Ok(revisions_str) => {
+            serde_json::from_str(&revisions_str).expect("failed to decode revisions file")
+        }
+        Err(_) => vec![],
+    }
+}
+
+pub fn save_revision(revision: &Revision) {
+    let mut revisions: Vec<Revision> = load_revisions();
+    revisions.push(revision.clone());
+
+    let revisions_str: String =
+        serde_json::to_string_pretty(&revisions).expect("failed to encode revisions");
+    fs::write(run_dir().join(REVISIONS_FILE), revisions_str).expect("failed to write revisions to file");
+}
+
 #[cfg(test)]
 
 mod tests {
@@ -113,10 +332,24 @@ mod tests {
 
     #[test]
     fn tests_extending_ai_function() {
-        let extended_msg: Message =
+        let extended_msgs: Vec<Message> =
             extend_ai_function(convert_user_input_to_goal, "dummy variable");
-        dbg!(&extended_msg);
-        assert_eq!(extended_msg.role, "system".to_string());
+        dbg!(&extended_msgs);
+        assert_eq!(extended_msgs[0].role, "system".to_string());
+        assert!(!extended_msgs[0].content.contains("dummy variable"));
+
+        let input_msg: &Message = extended_msgs.last().unwrap();
+        assert_eq!(input_msg.role, "user".to_string());
+        assert_eq!(input_msg.content, "<input>\ndummy variable\n</input>");
+    }
+
+    #[test]
+    fn tests_cancelled_calls_become_errors() {
+        let cancelled: Box<dyn std::error::Error> = cancelled_or_panic(Box::new(LlmError::Cancelled));
+        assert_eq!(cancelled.to_string(), "LLM call cancelled by user");
+
+        let timed_out = std::panic::catch_unwind(|| cancelled_or_panic(Box::new(LlmError::Timeout)));
+        assert!(timed_out.is_err());
     }
 
     #[tokio::test]
@@ -130,7 +363,8 @@ mod tests {
             "Defining User requirements",
             convert_user_input_to_goal,
         )
-        .await;
+        .await
+        .unwrap();
 
         assert!(res.len() > 20);
     }
@@ -1,2 +1,17 @@
+pub mod agent_definitions;
+pub mod agent_tools;
+pub mod build_cache;
+pub mod code_edits;
+pub mod code_review;
 pub mod command_line;
+pub mod evals;
+pub mod event_log;
 pub mod general;
+pub mod prompt_guard;
+pub mod prompts;
+pub mod run_report;
+pub mod safety_scan;
+pub mod scaffold;
+pub mod telemetry;
+pub mod templates;
+pub mod workspace_git;
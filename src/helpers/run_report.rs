@@ -0,0 +1,311 @@
+use crate::helpers::event_log::{load_events, EventRecord, RunEvent, EVENTS_FILE};
+use crate::models::agents::agent_traits::FactSheet;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//file inside the run directory
+pub const REPORT_FILE: &str = "report.html";
+
+pub const REPORT_STYLE: &str = "body { font-family: sans-serif; max-width: 1100px; margin: 2em auto; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }
+pre { background: #f6f8fa; padding: 1em; overflow-x: auto; font-size: 0.85em; }
+.ok { color: #1a7f37; } .failed { color: #cf222e; }";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TokenTotals {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn status_cell(status: &Option<u16>, error: &Option<String>) -> String {
+    match (status, error) {
+        (Some(200), _) => "<td class=\"ok\">200</td>".to_string(),
+        (Some(status), _) => format!("<td class=\"failed\">{}</td>", status),
+        (None, Some(error)) => format!("<td class=\"failed\">{}</td>", escape_html(error)),
+        (None, None) => "<td>-</td>".to_string(),
+    }
+}
+
+fn outcome_cell(succeeded: bool) -> &'static str {
+    if succeeded {
+        "<td class=\"ok\">succeeded</td>"
+    } else {
+        "<td class=\"failed\">failed</td>"
+    }
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    format!("{:.1}s", duration_ms as f64 / 1000.0)
+}
+
+pub fn token_totals(events: &[EventRecord]) -> TokenTotals {
+    let mut totals: TokenTotals = TokenTotals::default();
+    for record in events {
+        if let RunEvent::TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+            ..
+        } = &record.event
+        {
+            totals.prompt_tokens += *prompt_tokens as u64;
+            totals.completion_tokens += *completion_tokens as u64;
+            totals.total_tokens += *total_tokens as u64;
+        }
+    }
+    totals
+}
+
+fn render_overview(factsheet: &FactSheet, events: &[EventRecord]) -> String {
+    let mut html: String = String::from("<h2>Goal</h2>\n");
+    html.push_str(&format!("<p>{}</p>\n", escape_html(&factsheet.project_description)));
+
+    html.push_str("<h2>Scope</h2>\n<table>\n");
+    match factsheet.project_scope() {
+        Some(scope) => {
+            let flags: [(&str, bool); 4] = [
+                ("CRUD", scope.is_crud_required),
+                ("User login and logout", scope.is_user_login_and_logout),
+                ("External URLs", scope.is_external_url_required),
+                ("Database", scope.is_database_required),
+            ];
+            for (name, is_required) in flags {
+                html.push_str(&format!(
+                    "<tr><th>{}</th><td>{}</td></tr>\n",
+                    name,
+                    if is_required { "yes" } else { "no" }
+                ));
+            }
+        }
+        None => html.push_str("<tr><td>No scope was decided</td></tr>\n"),
+    }
+    if let Some(template) = factsheet.code_template() {
+        html.push_str(&format!(
+            "<tr><th>Code template</th><td>{}</td></tr>\n",
+            escape_html(&template)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    let llm_calls: Vec<u64> = events
+        .iter()
+        .filter_map(|record| match &record.event {
+            RunEvent::LlmResponse { duration_ms, .. } => Some(*duration_ms),
+            _ => None,
+        })
+        .collect();
+    let run_duration: u64 = match (events.first(), events.last()) {
+        (Some(first), Some(last)) => last.timestamp_ms.saturating_sub(first.timestamp_ms),
+        _ => 0,
+    };
+    let tokens: TokenTotals = token_totals(events);
+    let (cache_hits, cache_misses): (usize, usize) =
+        events.iter().fold((0, 0), |(hits, misses), record| match &record.event {
+            RunEvent::LlmCache { hit: true, .. } => (hits + 1, misses),
+            RunEvent::LlmCache { hit: false, .. } => (hits, misses + 1),
+            _ => (hits, misses),
+        });
+
+    html.push_str("<h2>Usage</h2>\n<table>\n");
+    html.push_str(&format!("<tr><th>Run time</th><td>{}</td></tr>\n", format_duration(run_duration)));
+    html.push_str(&format!(
+        "<tr><th>LLM calls</th><td>{} ({} waiting on the model)</td></tr>\n",
+        llm_calls.len(),
+        format_duration(llm_calls.iter().sum())
+    ));
+    html.push_str(&format!(
+        "<tr><th>Tokens</th><td>{} prompt, {} completion, {} total</td></tr>\n",
+        tokens.prompt_tokens, tokens.completion_tokens, tokens.total_tokens
+    ));
+    let queue_waits: Vec<u64> = events
+        .iter()
+        .filter_map(|record| match &record.event {
+            RunEvent::LlmQueued { wait_ms, .. } => Some(*wait_ms),
+            _ => None,
+        })
+        .collect();
+    html.push_str(&format!(
+        "<tr><th>Rate limit queue</th><td>{} waiting in total, {} at most</td></tr>\n",
+        format_duration(queue_waits.iter().sum()),
+        format_duration(queue_waits.iter().copied().max().unwrap_or(0))
+    ));
+    html.push_str(&format!(
+        "<tr><th>Response cache</th><td>{} hits, {} misses</td></tr>\n",
+        cache_hits, cache_misses
+    ));
+    html.push_str("</table>\n");
+
+    html
+}
+
+fn render_agents(events: &[EventRecord]) -> String {
+    let mut html: String = String::from("<h2>Agents</h2>\n<table>\n<tr><th>Agent</th><th>Outcome</th><th>Time</th></tr>\n");
+    let mut started: Vec<(&str, u64)> = vec![];
+
+    for record in events {
+        match &record.event {
+            RunEvent::AgentStarted { agent } => started.push((agent, record.timestamp_ms)),
+            RunEvent::AgentFinished { agent, succeeded, error } => {
+                let started_ms: u64 = started
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| name == agent)
+                    .map(|(_, timestamp_ms)| *timestamp_ms)
+                    .unwrap_or(record.timestamp_ms);
+
+                html.push_str(&format!(
+                    "<tr><td>{}</td>{}<td>{}</td></tr>\n",
+                    escape_html(agent),
+                    outcome_cell(*succeeded),
+                    format_duration(record.timestamp_ms.saturating_sub(started_ms))
+                ));
+                if let Some(error) = error {
+                    html.push_str(&format!("<tr><td colspan=\"3\"><pre>{}</pre></td></tr>\n", escape_html(error)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    html.push_str("</table>\n");
+    html
+}
+
+fn render_checks(factsheet: &FactSheet, events: &[EventRecord]) -> String {
+    let mut html: String = String::from("<h2>External URLs</h2>\n<table>\n<tr><th>URL</th><th>Status</th><th>Kept</th></tr>\n");
+    let kept_urls: Vec<String> = factsheet.external_urls().unwrap_or_default();
+
+    for record in events {
+        if let RunEvent::UrlCheck { url, status, error } = &record.event {
+            html.push_str(&format!(
+                "<tr><td>{}</td>{}<td>{}</td></tr>\n",
+                escape_html(url),
+                status_cell(status, error),
+                if kept_urls.contains(url) { "yes" } else { "no" }
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Builds</h2>\n<table>\n<tr><th>#</th><th>Command</th><th>Outcome</th><th>Time</th></tr>\n");
+    let builds = events.iter().filter_map(|record| match &record.event {
+        RunEvent::Build {
+            command,
+            succeeded,
+            output,
+            duration_ms,
+        } => Some((command, *succeeded, output, *duration_ms)),
+        _ => None,
+    });
+    for (attempt, (command, succeeded, output, duration_ms)) in builds.enumerate() {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td>{}<td>{}</td></tr>\n",
+            attempt + 1,
+            escape_html(command),
+            outcome_cell(succeeded),
+            format_duration(duration_ms)
+        ));
+        if !succeeded {
+            html.push_str(&format!("<tr><td colspan=\"4\"><pre>{}</pre></td></tr>\n", escape_html(output)));
+        }
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Endpoint tests</h2>\n<table>\n<tr><th>Route</th><th>Status</th></tr>\n");
+    for record in events {
+        if let RunEvent::EndpointResult { route, status, error } = &record.event {
+            html.push_str(&format!(
+                "<tr><td>{}</td>{}</tr>\n",
+                escape_html(route),
+                status_cell(status, error)
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+
+    html
+}
+
+//a single page with no external assets so it can be mailed around
+pub fn render_run_report(factsheet: &FactSheet, events: &[EventRecord]) -> String {
+    let mut html: String = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Run report</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>Run report</h1>\n",
+        REPORT_STYLE
+    );
+
+    html.push_str(&render_overview(factsheet, events));
+    html.push_str(&render_agents(events));
+    html.push_str(&render_checks(factsheet, events));
+
+    html.push_str("<h2>Final code</h2>\n");
+    match factsheet.backend_code() {
+        Some(code) => html.push_str(&format!("<pre>{}</pre>\n", escape_html(&code))),
+        None => html.push_str("<p>No code was generated</p>\n"),
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+pub fn write_run_report(run_dir: &Path, factsheet: &FactSheet) -> std::io::Result<PathBuf> {
+    let events: Vec<EventRecord> = load_events(&run_dir.join(EVENTS_FILE));
+    let path: PathBuf = run_dir.join(REPORT_FILE);
+    fs::write(&path, render_run_report(factsheet, &events))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp_ms: u64, event: RunEvent) -> EventRecord {
+        EventRecord { timestamp_ms, event }
+    }
+
+    #[test]
+    fn tests_render_run_report() {
+        let mut factsheet: FactSheet = FactSheet::new("build a <todo> app".to_string());
+        factsheet.set_external_urls("Solutions Architect", vec!["https://api.example.com".to_string()]);
+        factsheet.set_backend_code("Backend Developer", "fn main() { println!(\"<hi>\"); }".to_string());
+        factsheet.set_code_template("Solutions Architect", "actix_crud".to_string());
+
+        let events: Vec<EventRecord> = vec![
+            record(1_000, RunEvent::AgentStarted { agent: "Backend Developer".to_string() }),
+            record(2_000, RunEvent::UrlCheck { url: "https://api.example.com".to_string(), status: Some(200), error: None }),
+            record(3_000, RunEvent::Build { command: "cargo build".to_string(), succeeded: false, output: "error[E0425]".to_string(), duration_ms: 900 }),
+            record(4_000, RunEvent::Build { command: "cargo build".to_string(), succeeded: true, output: String::new(), duration_ms: 800 }),
+            record(5_000, RunEvent::EndpointResult { route: "/todos".to_string(), status: Some(500), error: None }),
+            record(5_500, RunEvent::TokenUsage { model: "gpt-3.5-turbo".to_string(), prompt_tokens: 100, completion_tokens: 50, total_tokens: 150 }),
+            record(6_000, RunEvent::AgentFinished { agent: "Backend Developer".to_string(), succeeded: true, error: None }),
+        ];
+
+        let html: String = render_run_report(&factsheet, &events);
+
+        assert!(html.contains("build a &lt;todo&gt; app"));
+        assert!(html.contains("&lt;hi&gt;"));
+        assert!(html.contains("<pre>error[E0425]</pre>"));
+        assert!(html.contains("<td>2</td><td>cargo build</td><td class=\"ok\">succeeded</td>"));
+        assert!(html.contains("<td>/todos</td><td class=\"failed\">500</td>"));
+        assert!(html.contains("100 prompt, 50 completion, 150 total"));
+        assert!(html.contains("<td>Backend Developer</td><td class=\"ok\">succeeded</td><td>5.0s</td>"));
+
+        //events stamped 0 when the clock could not be read must not underflow the durations
+        let skewed: Vec<EventRecord> = vec![
+            record(9_000, RunEvent::AgentStarted { agent: "Backend Developer".to_string() }),
+            record(0, RunEvent::AgentFinished { agent: "Backend Developer".to_string(), succeeded: false, error: None }),
+        ];
+        let html: String = render_run_report(&factsheet, &skewed);
+        assert!(html.contains("<td>Backend Developer</td><td class=\"failed\">failed</td><td>0.0s</td>"));
+    }
+}
@@ -0,0 +1,628 @@
+use crate::models::agents::agent_traits::{FindingSeverity, ReviewFinding};
+
+use proc_macro2::{TokenStream, TokenTree};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+pub const SAFETY_POLICY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/safety_policy.json");
+
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "0.0.0.0"];
+
+//the agents can write any rust file here and cargo compiles all of them
+const SCANNED_DIRS: [&str; 2] = ["src", "tests"];
+
+const FILE_WRITE_CALLS: [&str; 7] = [
+    "write",
+    "create",
+    "create_dir",
+    "create_dir_all",
+    "remove_file",
+    "remove_dir_all",
+    "rename",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanRule {
+    ProcessCommand,
+    UnsafeCode,
+    FileWriteOutsideProject,
+    EnvVarRead,
+    BuildScript,
+    AddedDependency,
+    UnknownHost,
+    UnparsableCode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SafetyPolicy {
+    #[serde(default)]
+    pub rules: HashMap<ScanRule, PolicyAction>,
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    #[serde(default)]
+    pub allowed_dependencies: Vec<String>,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        let rules: HashMap<ScanRule, PolicyAction> = HashMap::from([
+            (ScanRule::ProcessCommand, PolicyAction::Deny),
+            (ScanRule::UnsafeCode, PolicyAction::Deny),
+            (ScanRule::FileWriteOutsideProject, PolicyAction::Deny),
+            (ScanRule::EnvVarRead, PolicyAction::Warn),
+            (ScanRule::BuildScript, PolicyAction::Deny),
+            (ScanRule::AddedDependency, PolicyAction::Deny),
+            (ScanRule::UnknownHost, PolicyAction::Deny),
+            (ScanRule::UnparsableCode, PolicyAction::Deny),
+        ]);
+
+        let allowed_dependencies: Vec<String> = [
+            "reqwest",
+            "serde",
+            "serde_json",
+            "tokio",
+            "actix-web",
+            "async-trait",
+            "actix-cors",
+        ]
+        .iter()
+        .map(|dep| dep.to_string())
+        .collect();
+
+        Self {
+            rules,
+            allowed_hosts: vec![],
+            allowed_dependencies,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    //rules missing from a policy file fall back to the default action
+    pub fn action_for(&self, rule: ScanRule) -> PolicyAction {
+        match self.rules.get(&rule) {
+            Some(action) => *action,
+            None => *SafetyPolicy::default()
+                .rules
+                .get(&rule)
+                .unwrap_or(&PolicyAction::Deny),
+        }
+    }
+}
+
+pub fn load_safety_policy() -> SafetyPolicy {
+    match fs::read_to_string(SAFETY_POLICY_PATH) {
+        Ok(policy_str) => {
+            serde_json::from_str(&policy_str).expect("failed to decode safety policy file")
+        }
+        Err(_) => SafetyPolicy::default(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScanFinding {
+    pub rule: ScanRule,
+    pub action: PolicyAction,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl ScanFinding {
+    pub fn to_review_finding(&self) -> ReviewFinding {
+        let severity: FindingSeverity = match self.action {
+            PolicyAction::Deny => FindingSeverity::High,
+            _ => FindingSeverity::Medium,
+        };
+
+        ReviewFinding {
+            severity,
+            category: "static_scan".to_string(),
+            description: self.message.clone(),
+            line: self.line.map(|line| line as u32),
+        }
+    }
+}
+
+pub fn has_denied_findings(findings: &[ScanFinding]) -> bool {
+    findings
+        .iter()
+        .any(|finding| finding.action == PolicyAction::Deny)
+}
+
+pub fn format_scan_findings(findings: &[ScanFinding]) -> String {
+    findings
+        .iter()
+        .map(|finding| match finding.line {
+            Some(line) => format!("[{:?}] {:?} (line {}): {}", finding.action, finding.rule, line, finding.message),
+            None => format!("[{:?}] {:?}: {}", finding.action, finding.rule, finding.message),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+//local names the code gives to std::process and std::process::Command
+#[derive(Default)]
+struct ProcessImports {
+    process_names: Vec<String>,
+    command_names: Vec<String>,
+}
+
+impl ProcessImports {
+    fn collect_use_tree(&mut self, use_tree: &syn::UseTree, prefix: &mut Vec<String>) {
+        match use_tree {
+            syn::UseTree::Path(use_path) => {
+                prefix.push(use_path.ident.to_string());
+                self.collect_use_tree(&use_path.tree, prefix);
+                prefix.pop();
+            }
+            syn::UseTree::Name(use_name) => self.add_import(prefix, &use_name.ident, &use_name.ident),
+            syn::UseTree::Rename(use_rename) => self.add_import(prefix, &use_rename.ident, &use_rename.rename),
+            syn::UseTree::Glob(_) => {
+                if prefix.as_slice() == ["std", "process"] {
+                    self.command_names.push("Command".to_string());
+                }
+            }
+            syn::UseTree::Group(use_group) => {
+                for item in &use_group.items {
+                    self.collect_use_tree(item, prefix);
+                }
+            }
+        }
+    }
+
+    fn add_import(&mut self, prefix: &[String], ident: &syn::Ident, local_name: &syn::Ident) {
+        match (prefix, ident.to_string().as_str()) {
+            ([std, process], "Command") if std == "std" && process == "process" => {
+                self.command_names.push(local_name.to_string())
+            }
+            ([std], "process") if std == "std" => self.process_names.push(local_name.to_string()),
+            ([std, process], "self") if std == "std" && process == "process" => {
+                let local_name: String = match ident == local_name {
+                    true => "process".to_string(),
+                    false => local_name.to_string(),
+                };
+                self.process_names.push(local_name)
+            }
+            _ => {}
+        }
+    }
+
+    fn is_command(&self, segments: &[String]) -> bool {
+        segments.windows(3).any(|window| window == ["std", "process", "Command"])
+            || (segments.len() >= 2 && self.process_names.contains(&segments[0]) && segments[1] == "Command")
+            || segments.first().is_some_and(|first| self.command_names.contains(first))
+    }
+}
+
+//imports are collected first so a use at the bottom of the file still counts
+impl<'ast> Visit<'ast> for ProcessImports {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.collect_use_tree(&item.tree, &mut vec![]);
+    }
+}
+
+struct CodeScanner<'a> {
+    allowed_hosts: &'a [String],
+    imports: ProcessImports,
+    hits: Vec<(ScanRule, String, usize)>,
+}
+
+impl<'a> CodeScanner<'a> {
+    fn flag(&mut self, rule: ScanRule, message: String, span: proc_macro2::Span) {
+        self.hits.push((rule, message, span.start().line));
+    }
+
+    fn check_url(&mut self, value: &str, span: proc_macro2::Span) {
+        if !value.starts_with("http://") && !value.starts_with("https://") {
+            return;
+        }
+
+        let host: Option<String> = Url::parse(value)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()));
+
+        match host {
+            Some(host) => {
+                let is_allowed: bool = LOCAL_HOSTS.contains(&host.as_str())
+                    || self.allowed_hosts.iter().any(|allowed| allowed == &host);
+
+                if !is_allowed {
+                    self.flag(
+                        ScanRule::UnknownHost,
+                        format!("calls host '{}' which is not in the external urls", host),
+                        span,
+                    );
+                }
+            }
+            None => self.flag(
+                ScanRule::UnknownHost,
+                format!("calls url '{}' with no recognisable host", value),
+                span,
+            ),
+        }
+    }
+
+    //string literals inside macros such as format! are not parsed into expressions
+    fn scan_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => self.scan_tokens(group.stream()),
+                TokenTree::Literal(literal) => {
+                    let span: proc_macro2::Span = literal.span();
+                    if let Ok(lit_str) = syn::parse2::<syn::LitStr>(TokenTree::Literal(literal).into()) {
+                        self.check_url(&lit_str.value(), span);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn path_segments(path: &syn::Path) -> Vec<String> {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect()
+}
+
+fn is_path_outside_project(path: &str) -> bool {
+    path.starts_with('/')
+        || path.starts_with('~')
+        || path.starts_with('\\')
+        || path.get(1..3) == Some(":\\")
+        || Path::new(path)
+            .components()
+            .any(|component| component == std::path::Component::ParentDir)
+}
+
+impl<'a, 'ast> Visit<'ast> for CodeScanner<'a> {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments: Vec<String> = path_segments(path);
+
+        if self.imports.is_command(&segments) {
+            self.flag(
+                ScanRule::ProcessCommand,
+                format!("uses '{}' to run processes", segments.join("::")),
+                path.span(),
+            );
+        }
+
+        if segments.windows(2).any(|pair| {
+            pair[0] == "env" && ["var", "var_os", "vars", "vars_os"].contains(&pair[1].as_str())
+        }) {
+            self.flag(
+                ScanRule::EnvVarRead,
+                format!("reads environment variables with '{}'", segments.join("::")),
+                path.span(),
+            );
+        }
+
+        visit::visit_path(self, path);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = call.func.as_ref() {
+            let segments: Vec<String> = path_segments(&func.path);
+            let is_fs_call: bool = segments.len() >= 2
+                && ["fs", "File"].contains(&segments[segments.len() - 2].as_str())
+                && FILE_WRITE_CALLS.contains(&segments[segments.len() - 1].as_str());
+
+            if is_fs_call {
+                match call.args.first() {
+                    Some(syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(lit_str),
+                        ..
+                    })) => {
+                        if is_path_outside_project(&lit_str.value()) {
+                            self.flag(
+                                ScanRule::FileWriteOutsideProject,
+                                format!("writes to '{}' outside the project", lit_str.value()),
+                                call.span(),
+                            );
+                        }
+                    }
+                    _ => self.flag(
+                        ScanRule::FileWriteOutsideProject,
+                        format!("'{}' is called with a path computed at runtime", segments.join("::")),
+                        call.span(),
+                    ),
+                }
+            }
+        }
+
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_unsafe(&mut self, expr: &'ast syn::ExprUnsafe) {
+        self.flag(ScanRule::UnsafeCode, "contains an unsafe block".to_string(), expr.span());
+        visit::visit_expr_unsafe(self, expr);
+    }
+
+    fn visit_signature(&mut self, sig: &'ast syn::Signature) {
+        if sig.unsafety.is_some() {
+            self.flag(
+                ScanRule::UnsafeCode,
+                format!("declares unsafe fn '{}'", sig.ident),
+                sig.span(),
+            );
+        }
+        visit::visit_signature(self, sig);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        if item.unsafety.is_some() {
+            self.flag(ScanRule::UnsafeCode, "contains an unsafe impl".to_string(), item.span());
+        }
+        visit::visit_item_impl(self, item);
+    }
+
+    fn visit_lit_str(&mut self, lit_str: &'ast syn::LitStr) {
+        self.check_url(&lit_str.value(), lit_str.span());
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.scan_tokens(mac.tokens.clone());
+        visit::visit_macro(self, mac);
+    }
+}
+
+fn url_hosts(urls: &[String]) -> Vec<String> {
+    urls.iter()
+        .filter_map(|url| Url::parse(url).ok())
+        .filter_map(|url| url.host_str().map(|host| host.to_string()))
+        .collect()
+}
+
+//code that cannot be parsed cannot be checked, so it is a finding rather than a pass
+pub fn scan_code(code: &str, external_urls: &[String], policy: &SafetyPolicy) -> Vec<ScanFinding> {
+    let syntax_tree: syn::File = match syn::parse_file(code) {
+        Ok(syntax_tree) => syntax_tree,
+        Err(e) => {
+            return vec![ScanFinding {
+                rule: ScanRule::UnparsableCode,
+                action: policy.action_for(ScanRule::UnparsableCode),
+                message: format!("code cannot be parsed for scanning: {}", e),
+                line: Some(e.span().start().line),
+            }]
+            .into_iter()
+            .filter(|finding| finding.action != PolicyAction::Allow)
+            .collect();
+        }
+    };
+
+    let mut allowed_hosts: Vec<String> = url_hosts(external_urls);
+    allowed_hosts.extend(policy.allowed_hosts.iter().cloned());
+
+    let mut imports: ProcessImports = ProcessImports::default();
+    imports.visit_file(&syntax_tree);
+
+    let mut scanner: CodeScanner = CodeScanner {
+        allowed_hosts: &allowed_hosts,
+        imports,
+        hits: vec![],
+    };
+    scanner.visit_file(&syntax_tree);
+
+    let findings: Vec<ScanFinding> = scanner
+        .hits
+        .into_iter()
+        .map(|(rule, message, line)| ScanFinding {
+            rule,
+            action: policy.action_for(rule),
+            message,
+            line: Some(line),
+        })
+        .filter(|finding| finding.action != PolicyAction::Allow)
+        .collect();
+
+    findings
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+//scans every rust file cargo can compile plus the build script and manifest
+pub fn scan_project_files(project_dir: &Path, external_urls: &[String], policy: &SafetyPolicy) -> Vec<ScanFinding> {
+    let mut findings: Vec<ScanFinding> = vec![];
+
+    let mut files: Vec<PathBuf> = vec![];
+    for dir in SCANNED_DIRS {
+        rust_files(&project_dir.join(dir), &mut files);
+    }
+
+    for path in files {
+        let relative_path: String = path.strip_prefix(project_dir).unwrap_or(&path).display().to_string();
+        let code: String = fs::read_to_string(&path).unwrap_or_default();
+
+        findings.extend(scan_code(&code, external_urls, policy).into_iter().map(|mut finding| {
+            finding.message = format!("{}: {}", relative_path, finding.message);
+            finding
+        }));
+    }
+
+    if project_dir.join("build.rs").exists() {
+        findings.push(ScanFinding {
+            rule: ScanRule::BuildScript,
+            action: policy.action_for(ScanRule::BuildScript),
+            message: "project contains a build.rs script".to_string(),
+            line: None,
+        });
+    }
+
+    if let Ok(cargo_str) = fs::read_to_string(project_dir.join("Cargo.toml")) {
+        if let Ok(manifest) = cargo_str.parse::<toml::Table>() {
+            let dependencies = ["dependencies", "dev-dependencies", "build-dependencies"]
+                .iter()
+                .filter_map(|section| manifest.get(*section).and_then(|deps| deps.as_table()))
+                .flat_map(|deps| deps.keys());
+
+            for dependency in dependencies {
+                let is_allowed: bool = policy
+                    .allowed_dependencies
+                    .iter()
+                    .any(|allowed| allowed.replace('_', "-") == dependency.replace('_', "-"));
+
+                if !is_allowed {
+                    findings.push(ScanFinding {
+                        rule: ScanRule::AddedDependency,
+                        action: policy.action_for(ScanRule::AddedDependency),
+                        message: format!("Cargo.toml depends on '{}' which is not allowed", dependency),
+                        line: None,
+                    });
+                }
+            }
+        }
+    }
+
+    findings
+        .into_iter()
+        .filter(|finding| finding.action != PolicyAction::Allow)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned_rules(code: &str, external_urls: &[String]) -> Vec<ScanRule> {
+        scan_code(code, external_urls, &SafetyPolicy::default())
+            .iter()
+            .map(|finding| finding.rule)
+            .collect()
+    }
+
+    #[test]
+    fn tests_scan_flags_unsafe_patterns() {
+        let code: &str = r#"
+use std::process::Command;
+
+fn main() {
+    let key = std::env::var("SECRET").unwrap();
+    Command::new("rm").arg("-rf").spawn().unwrap();
+    std::fs::write("/etc/passwd", key).unwrap();
+    unsafe { libc_call() };
+}
+"#;
+
+        let rules: Vec<ScanRule> = scanned_rules(code, &[]);
+
+        assert!(rules.contains(&ScanRule::ProcessCommand));
+        assert!(rules.contains(&ScanRule::EnvVarRead));
+        assert!(rules.contains(&ScanRule::FileWriteOutsideProject));
+        assert!(rules.contains(&ScanRule::UnsafeCode));
+    }
+
+    #[test]
+    fn tests_scan_checks_hosts_against_external_urls() {
+        let code: &str = r#"
+async fn prices() -> String {
+    let url = format!("https://api.binance.com/api/v3/ticker?symbol={}", "BTC");
+    let other = "https://evil.example.com/collect";
+    reqwest::get("http://localhost:8080/health").await.unwrap();
+    url + other
+}
+"#;
+        let external_urls: Vec<String> = vec!["https://api.binance.com/api/v3/exchangeInfo".to_string()];
+
+        let findings: Vec<ScanFinding> =
+            scan_code(code, &external_urls, &SafetyPolicy::default());
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, ScanRule::UnknownHost);
+        assert_eq!(findings[0].line, Some(4));
+        assert!(has_denied_findings(&findings));
+    }
+
+    #[test]
+    fn tests_policy_file_overrides_rules() {
+        let policy: SafetyPolicy =
+            serde_json::from_str(r#"{ "rules": { "process_command": "allow", "unsafe_code": "warn" } }"#)
+                .unwrap();
+        let code: &str = "fn main() { std::process::exit(0); unsafe { run() }; std::fs::write(\"db.json\", \"[]\").unwrap(); }";
+
+        let findings: Vec<ScanFinding> = scan_code(code, &[], &policy);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, ScanRule::UnsafeCode);
+        assert_eq!(findings[0].action, PolicyAction::Warn);
+        assert!(!has_denied_findings(&findings));
+        assert_eq!(policy.action_for(ScanRule::UnknownHost), PolicyAction::Deny);
+    }
+
+    #[test]
+    fn tests_scan_matches_only_process_commands() {
+        let code: &str = r#"
+use clap::Command;
+use std::process::{self as proc, Stdio};
+
+mod process {
+    pub fn handle(todo: &str) -> String { todo.to_string() }
+}
+
+fn main() {
+    let todo = process::handle("walk");
+    let cli = Command::new("todos");
+    let child = proc::Command::new("sh");
+}
+"#;
+
+        let findings: Vec<ScanFinding> = scan_code(code, &[], &SafetyPolicy::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, ScanRule::ProcessCommand);
+        assert_eq!(findings[0].line, Some(12));
+
+        let aliased: &str = "fn main() { Cmd::new(\"sh\"); }\nuse std::process::Command as Cmd;";
+        assert_eq!(scanned_rules(aliased, &[]), vec![ScanRule::ProcessCommand]);
+
+        let broken: Vec<ScanFinding> = scan_code("fn main() { let x = ; }", &[], &SafetyPolicy::default());
+        assert_eq!(broken[0].rule, ScanRule::UnparsableCode);
+        assert!(has_denied_findings(&broken));
+    }
+
+    #[test]
+    fn tests_project_scan_covers_every_rust_file() {
+        let project_dir: PathBuf = std::env::temp_dir().join(format!("auto_gippity_scan_{}", std::process::id()));
+        fs::create_dir_all(project_dir.join("src/nested")).unwrap();
+        fs::create_dir_all(project_dir.join("tests")).unwrap();
+        fs::write(project_dir.join("src/main.rs"), "mod nested;\nfn main() {}").unwrap();
+        fs::write(project_dir.join("src/nested/mod.rs"), "pub fn run() { std::process::Command::new(\"sh\"); }").unwrap();
+        fs::write(project_dir.join("tests/api_tests.rs"), "fn check() { unsafe { run() }; }").unwrap();
+
+        let findings: Vec<ScanFinding> = scan_project_files(&project_dir, &[], &SafetyPolicy::default());
+        fs::remove_dir_all(&project_dir).unwrap();
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].rule, ScanRule::ProcessCommand);
+        assert!(findings[0].message.starts_with("src/nested/mod.rs:"));
+        assert_eq!(findings[1].rule, ScanRule::UnsafeCode);
+        assert!(findings[1].message.starts_with("tests/api_tests.rs:"));
+    }
+}
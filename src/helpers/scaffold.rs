@@ -0,0 +1,151 @@
+use crate::helpers::build_cache::{is_template_vendored, vendor_source_config, vendored_lock_file};
+use crate::helpers::templates::CodeTemplate;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const WORKSPACES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/workspaces");
+
+const PROJECT_DIR: &str = "project";
+
+const SHARED_TARGET_DIR: &str = "target";
+
+static RUN_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+//AUTO_GIPPITY_WORKSPACES moves every run somewhere other than the crate directory
+pub fn workspaces_root() -> PathBuf {
+    match env::var("AUTO_GIPPITY_WORKSPACES") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from(WORKSPACES_PATH),
+    }
+}
+
+//every build of every run shares one target directory so dependencies compile once
+pub fn shared_target_dir() -> PathBuf {
+    workspaces_root().join(SHARED_TARGET_DIR)
+}
+
+pub fn set_run_dir(run_dir: &Path) {
+    *RUN_DIR.write().unwrap() = Some(run_dir.to_path_buf());
+}
+
+pub fn run_dir() -> PathBuf {
+    RUN_DIR
+        .read()
+        .unwrap()
+        .clone()
+        .expect("no run directory, create one with create_run_dir first")
+}
+
+pub fn project_dir() -> PathBuf {
+    run_dir().join(PROJECT_DIR)
+}
+
+//a fresh directory for this run's project, fact sheet and artefacts
+pub fn create_run_dir() -> PathBuf {
+    let timestamp: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+
+    let run_dir: PathBuf = workspaces_root().join(format!("run_{}", timestamp));
+    fs::create_dir_all(&run_dir).expect("failed to create run directory");
+
+    set_run_dir(&run_dir);
+    run_dir
+}
+
+pub fn cargo_manifest(template: &CodeTemplate) -> Result<String, Box<dyn std::error::Error>> {
+    let mut package: toml::Table = toml::Table::new();
+    package.insert("name".to_string(), toml::Value::from("web_server"));
+    package.insert("version".to_string(), toml::Value::from("0.1.0"));
+    package.insert("edition".to_string(), toml::Value::from("2021"));
+
+    let mut dependencies: toml::Table = toml::Table::new();
+    for (name, spec) in &template.manifest.dependencies {
+        dependencies.insert(name.clone(), toml::Value::try_from(spec)?);
+    }
+
+    let mut manifest: toml::Table = toml::Table::new();
+    manifest.insert("package".to_string(), toml::Value::Table(package));
+    manifest.insert("dependencies".to_string(), toml::Value::Table(dependencies));
+
+    //keeps the generated crate out of any workspace above it
+    manifest.insert("workspace".to_string(), toml::Value::Table(toml::Table::new()));
+
+    Ok(toml::to_string(&manifest)?)
+}
+
+//cargo reads paths as TOML strings, which only hold UTF-8
+pub fn toml_path(path: &Path) -> Result<toml::Value, Box<dyn std::error::Error>> {
+    path.to_str()
+        .map(|path| toml::Value::String(path.to_string()))
+        .ok_or_else(|| format!("path is not valid UTF-8: {}", path.display()).into())
+}
+
+//writes a buildable crate for the template into the project directory of the run
+pub fn scaffold_project(project_dir: &Path, template: &CodeTemplate) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(project_dir.join("src"))?;
+    fs::create_dir_all(project_dir.join(".cargo"))?;
+
+    fs::write(project_dir.join("Cargo.toml"), cargo_manifest(template)?)?;
+    fs::write(project_dir.join("src").join("main.rs"), template.read_code())?;
+    fs::write(project_dir.join(".gitignore"), "target/\n")?;
+
+    let mut build: toml::Table = toml::Table::new();
+    build.insert("target-dir".to_string(), toml_path(&shared_target_dir())?);
+    let mut cargo_config: toml::Table = toml::Table::new();
+    cargo_config.insert("build".to_string(), toml::Value::Table(build));
+
+    //vendored templates build offline against the lock file they were vendored with
+    if is_template_vendored(template) {
+        cargo_config.insert("source".to_string(), toml::Value::Table(vendor_source_config(template)?));
+        fs::copy(vendored_lock_file(template), project_dir.join("Cargo.lock"))?;
+    }
+
+    fs::write(project_dir.join(".cargo").join("config.toml"), toml::to_string(&cargo_config)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::templates::{TemplateRegistry, DEFAULT_TEMPLATE, TEMPLATES_PATH};
+
+    #[test]
+    fn tests_scaffold_project() {
+        let registry: TemplateRegistry = TemplateRegistry::load(Path::new(TEMPLATES_PATH)).unwrap();
+        let template: &CodeTemplate = registry.get(DEFAULT_TEMPLATE).unwrap();
+        let project_dir: PathBuf = env::temp_dir().join(format!("auto_gippity_scaffold_{}", std::process::id()));
+
+        scaffold_project(&project_dir, template).unwrap();
+
+        let manifest: toml::Table = fs::read_to_string(project_dir.join("Cargo.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let dependencies: &toml::Table = manifest["dependencies"].as_table().unwrap();
+
+        assert_eq!(manifest["package"]["name"].as_str(), Some("web_server"));
+        assert_eq!(dependencies.len(), template.manifest.dependencies.len());
+        assert_eq!(dependencies["serde"]["features"][0].as_str(), Some("derive"));
+        assert_eq!(
+            fs::read_to_string(project_dir.join("src").join("main.rs")).unwrap(),
+            template.read_code()
+        );
+        let cargo_config: toml::Table = fs::read_to_string(project_dir.join(".cargo").join("config.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            cargo_config["build"]["target-dir"].as_str(),
+            shared_target_dir().to_str()
+        );
+
+        fs::remove_dir_all(&project_dir).unwrap();
+    }
+}
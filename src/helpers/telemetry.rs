@@ -0,0 +1,152 @@
+use std::env;
+use std::process::{Child, Command, Output};
+use tracing::{info_span, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+//AUTO_GIPPITY_LOG takes EnvFilter directives, e.g. "auto_gippity=debug"
+pub const LOG_ENV: &str = "AUTO_GIPPITY_LOG";
+
+//the OTLP exporter is switched on by pointing this at a collector, e.g. http://localhost:4317
+#[cfg(feature = "otlp")]
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+const DEFAULT_TRACE_FILTER: &str = "auto_gippity=info";
+
+//flushes exported spans when main returns
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+fn trace_filter() -> EnvFilter {
+    EnvFilter::new(env::var(LOG_ENV).unwrap_or(DEFAULT_TRACE_FILTER.to_string()))
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_tracer_provider() -> Option<opentelemetry_sdk::trace::TracerProvider> {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+
+    env::var(OTLP_ENDPOINT_ENV).ok()?;
+
+    //the exporter reads the endpoint from the environment itself
+    let exporter: SpanExporter = match SpanExporter::builder().with_tonic().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("failed to create OTLP exporter, traces will not be exported: {}", e);
+            return None;
+        }
+    };
+
+    Some(
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new("service.name", "auto_gippity")]))
+            .build(),
+    )
+}
+
+//terminal output stays as it is unless AUTO_GIPPITY_LOG asks for log lines on stderr
+pub fn init_tracing() -> TelemetryGuard {
+    let fmt_layer = env::var(LOG_ENV)
+        .ok()
+        .map(|_| fmt::layer().with_writer(std::io::stderr).with_filter(trace_filter()));
+
+    let registry = tracing_subscriber::registry().with(fmt_layer);
+
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let provider = otlp_tracer_provider();
+        let otel_layer = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("auto_gippity"))
+                .with_filter(trace_filter())
+        });
+
+        registry.with(otel_layer).init();
+        TelemetryGuard { provider }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.init();
+        TelemetryGuard {}
+    }
+}
+
+fn subprocess_span(command: &Command) -> Span {
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+
+    info_span!(
+        "subprocess",
+        program = %command.get_program().to_string_lossy(),
+        args = %args.join(" "),
+        exit_code = tracing::field::Empty,
+    )
+}
+
+//runs a subprocess to completion inside its own span
+pub fn traced_output(command: &mut Command) -> std::io::Result<Output> {
+    let span: Span = subprocess_span(command);
+    let _entered = span.enter();
+
+    let output: Output = command.output()?;
+    if let Some(code) = output.status.code() {
+        span.record("exit_code", code);
+    }
+    Ok(output)
+}
+
+//long running subprocesses only get a span for starting them
+pub fn traced_spawn(command: &mut Command) -> std::io::Result<Child> {
+    subprocess_span(command).in_scope(|| command.spawn())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::Context;
+
+    struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for SpanNames {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(attrs.metadata().name().to_string());
+        }
+    }
+
+    #[test]
+    fn tests_subprocess_runs_in_span() {
+        let names: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let subscriber = tracing_subscriber::registry().with(SpanNames(names.clone()));
+
+        let output: Output = tracing::subscriber::with_default(subscriber, || {
+            traced_output(Command::new("git").arg("--version")).unwrap()
+        });
+
+        assert!(output.status.success());
+        assert_eq!(*names.lock().unwrap(), vec!["subprocess".to_string()]);
+    }
+}
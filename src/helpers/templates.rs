@@ -0,0 +1,168 @@
+use crate::models::agents::agent_traits::ProjectScope;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const TEMPLATES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/templates");
+
+pub const DEFAULT_TEMPLATE: &str = "actix_crud";
+
+const MANIFEST_FILE: &str = "template.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TemplateManifest {
+    pub name: String,
+    pub framework: String,
+    pub description: String,
+    pub capabilities: Vec<String>,
+    pub code_file: String,
+    pub dependencies: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeTemplate {
+    pub manifest: TemplateManifest,
+    pub dir: PathBuf,
+}
+
+impl CodeTemplate {
+    pub fn read_code(&self) -> String {
+        fs::read_to_string(self.dir.join(&self.manifest.code_file))
+            .expect("failed to read code template contents!")
+    }
+
+    pub fn allowed_libraries(&self) -> Vec<String> {
+        self.manifest.dependencies.keys().cloned().collect()
+    }
+
+    //the line of libraries the prompts tell the model it may use
+    pub fn libraries_prompt(&self) -> String {
+        format!(
+            "FRAMEWORK: {} \n INSTALLED_LIBRARIES: {} \n",
+            self.manifest.framework,
+            self.allowed_libraries().join(", ")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateRegistry {
+    templates: Vec<CodeTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut templates: Vec<CodeTemplate> = vec![];
+
+        for entry in fs::read_dir(dir)? {
+            let template_dir: PathBuf = entry?.path();
+            let manifest_path: PathBuf = template_dir.join(MANIFEST_FILE);
+            if !manifest_path.exists() {
+                continue;
+            }
+
+            let manifest: TemplateManifest = serde_json::from_str(&fs::read_to_string(&manifest_path)?)
+                .map_err(|e| format!("invalid template manifest {}: {}", manifest_path.display(), e))?;
+
+            templates.push(CodeTemplate {
+                manifest,
+                dir: template_dir,
+            });
+        }
+
+        templates.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+        Ok(Self { templates })
+    }
+
+    //AUTO_GIPPITY_TEMPLATES points at a custom template directory
+    pub fn load_default() -> Self {
+        let dir: String = env::var("AUTO_GIPPITY_TEMPLATES").unwrap_or(TEMPLATES_PATH.to_string());
+        TemplateRegistry::load(Path::new(&dir)).expect("failed to load code templates")
+    }
+
+    pub fn all(&self) -> &[CodeTemplate] {
+        &self.templates
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CodeTemplate> {
+        self.templates.iter().find(|template| template.manifest.name == name)
+    }
+
+    //prefers a template covering every required capability with the fewest unused ones
+    pub fn select(&self, scope: &ProjectScope) -> &CodeTemplate {
+        let required: Vec<&str> = required_capabilities(scope);
+
+        self.templates
+            .iter()
+            .filter(|template| {
+                required
+                    .iter()
+                    .all(|capability| template.manifest.capabilities.iter().any(|c| c == capability))
+            })
+            .min_by_key(|template| template.manifest.capabilities.len() - required.len())
+            .or_else(|| self.get(DEFAULT_TEMPLATE))
+            .or_else(|| self.templates.first())
+            .expect("no code templates found")
+    }
+}
+
+pub fn required_capabilities(scope: &ProjectScope) -> Vec<&'static str> {
+    let mut capabilities: Vec<&'static str> = vec![];
+
+    if scope.is_crud_required {
+        capabilities.push("crud");
+    }
+    if scope.is_user_login_and_logout {
+        capabilities.push("user_login");
+    }
+    if scope.is_external_url_required {
+        capabilities.push("external_urls");
+    }
+    if scope.is_database_required {
+        capabilities.push("database");
+    }
+    if capabilities.is_empty() {
+        capabilities.push("static_site");
+    }
+
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(crud: bool, login: bool, external: bool, database: bool) -> ProjectScope {
+        ProjectScope {
+            is_crud_required: crud,
+            is_user_login_and_logout: login,
+            is_external_url_required: external,
+            is_database_required: database,
+        }
+    }
+
+    #[test]
+    fn tests_bundled_templates_load() {
+        let registry: TemplateRegistry = TemplateRegistry::load(Path::new(TEMPLATES_PATH)).unwrap();
+        let template: &CodeTemplate = registry.get(DEFAULT_TEMPLATE).unwrap();
+
+        assert!(template.read_code().contains("HttpServer"));
+        assert!(template.allowed_libraries().contains(&"actix-web".to_string()));
+        assert!(template.libraries_prompt().starts_with("FRAMEWORK: actix-web"));
+    }
+
+    #[test]
+    fn tests_select_template_by_scope() {
+        let registry: TemplateRegistry = TemplateRegistry::load(Path::new(TEMPLATES_PATH)).unwrap();
+
+        let select_name = |scope: ProjectScope| registry.select(&scope).manifest.name.clone();
+
+        assert_eq!(select_name(scope(true, true, true, false)), "actix_crud");
+        assert_eq!(select_name(scope(true, false, false, true)), "axum_sqlite");
+        assert_eq!(select_name(scope(false, false, true, false)), "static_api_proxy");
+        assert_eq!(select_name(scope(true, true, true, true)), DEFAULT_TEMPLATE);
+    }
+}
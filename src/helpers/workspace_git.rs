@@ -0,0 +1,122 @@
+use crate::helpers::telemetry::traced_output;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+#[derive(Debug)]
+pub struct WorkspaceRepo {
+    path: PathBuf,
+}
+
+impl WorkspaceRepo {
+    //initialises a git repository in the generated project unless it already has one
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let repo: WorkspaceRepo = WorkspaceRepo {
+            path: path.to_path_buf(),
+        };
+
+        if !repo.path.join(".git").exists() {
+            repo.git(&["init", "--quiet"])?;
+        }
+
+        let gitignore_path: PathBuf = repo.path.join(".gitignore");
+        if !gitignore_path.exists() {
+            fs::write(gitignore_path, "target/\n")?;
+        }
+
+        repo.git(&["config", "user.name", "auto_gippity"])?;
+        repo.git(&["config", "user.email", "auto_gippity@localhost"])?;
+
+        Ok(repo)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Output, Box<dyn std::error::Error>> {
+        let output: Output = traced_output(
+            Command::new("git")
+                .args(args)
+                .current_dir(&self.path)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )?;
+
+        if !output.status.success() {
+            let error_str: String = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(format!("git {} failed: {}", args.join(" "), error_str.trim()).into());
+        }
+
+        Ok(output)
+    }
+
+    //commits every change in the workspace, even when nothing changed, so each step is on record
+    pub fn commit_step(
+        &self,
+        agent: &str,
+        ai_function: &str,
+        outcome: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let message: String = format!(
+            "{}: {} ({})\n\nAgent: {}\nAI-Function: {}\nOutcome: {}\n",
+            agent, ai_function, outcome, agent, ai_function, outcome
+        );
+
+        self.git(&["add", "-A"])?;
+        self.git(&["commit", "--quiet", "--allow-empty", "-m", message.as_str()])?;
+
+        self.head_commit()
+    }
+
+    pub fn head_commit(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let output: Output = self.git(&["rev-parse", "HEAD"])?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    //restores the files of an earlier commit as a new commit so the failed attempts stay in history
+    pub fn rollback_to(&self, commit: &str, agent: &str) -> Result<String, Box<dyn std::error::Error>> {
+        //tracking everything first lets read-tree also delete the files added since that commit
+        self.git(&["add", "-A"])?;
+        self.git(&["read-tree", "-u", "--reset", commit])?;
+
+        let outcome: String = format!("rolled back to {}", commit);
+        self.commit_step(agent, "rollback", outcome.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn tests_workspace_repo_commits_and_rolls_back() {
+        let workspace_path: PathBuf = env::temp_dir().join(format!("auto_gippity_git_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&workspace_path);
+        fs::create_dir_all(&workspace_path).unwrap();
+        let main_path: PathBuf = workspace_path.join("main.rs");
+
+        let repo: WorkspaceRepo = WorkspaceRepo::open(&workspace_path).unwrap();
+
+        fs::write(&main_path, "fn main() {}").unwrap();
+        let building_commit: String = repo
+            .commit_step("Backend Developer", "print_backend_webserver_code", "build succeeded")
+            .unwrap();
+
+        fs::write(&main_path, "fn main() { broken").unwrap();
+        fs::write(workspace_path.join("api_tests.rs"), "#[test] fn added() {}").unwrap();
+        repo.commit_step("Backend Developer", "print_fixed_code", "build failed")
+            .unwrap();
+        fs::write(workspace_path.join("scratch.rs"), "not committed").unwrap();
+
+        repo.rollback_to(&building_commit, "Backend Developer").unwrap();
+        assert_eq!(fs::read_to_string(&main_path).unwrap(), "fn main() {}");
+        assert!(!workspace_path.join("api_tests.rs").exists());
+        assert!(!workspace_path.join("scratch.rs").exists());
+
+        let log: Output = repo.git(&["log", "--format=%s"]).unwrap();
+        let log_str: String = String::from_utf8_lossy(&log.stdout).to_string();
+        assert_eq!(log_str.lines().count(), 3);
+        assert!(log_str.starts_with("Backend Developer: rollback (rolled back to"));
+
+        fs::remove_dir_all(&workspace_path).unwrap();
+    }
+}
@@ -10,18 +10,241 @@ mod apis;
 mod helpers;
 mod models;
 
+use apis::call_request::handle_ctrl_c;
+use apis::llm_cache::{cache_stats, disable_cache, is_cache_enabled};
+use apis::rate_limiter::{QueueMetrics, RATE_LIMITER};
+use apis::tool_calling::ToolSet;
+use helpers::agent_tools::{agent_toolset, load_tool_permissions};
+use helpers::build_cache::{is_offline_build, warm_up_template};
 use helpers::command_line::get_user_response;
+use helpers::evals::{load_cases, provider_by_name, run_evals, write_eval_report, EvalCase, EvalProvider, EvalRun, EVALS_PATH};
+use helpers::event_log::start_event_log;
+use helpers::general::{active_code_template, guard_input, load_factsheet, save_factsheet, FACTSHEET_FILE};
+use helpers::run_report::write_run_report;
+use helpers::prompts::PromptRegistry;
+use helpers::scaffold::{create_run_dir, project_dir, set_run_dir, workspaces_root};
+use helpers::telemetry::init_tracing;
+use helpers::templates::{CodeTemplate, TemplateRegistry};
+use models::agents::agent_react::{AgentReAct, ReActLimits};
+use models::agents::agent_traits::{FactSheet, SpecialFunctions};
 use models::agents_manager::managing_agent::ManagingAgent;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+async fn revise_project(run_dir: &Path) {
+    set_run_dir(run_dir);
+    start_event_log(run_dir).expect("failed to start run event log");
+    let factsheet: FactSheet = load_factsheet();
+
+    let mut manage_agent: ManagingAgent = ManagingAgent::from_factsheet(factsheet);
+
+    loop {
+        let change_request = get_user_response("what would you like to change? (leave empty to finish)");
+        if change_request.is_empty() {
+            break;
+        }
+
+        //the change request is pasted into the routing and revision prompts
+        let injection_matches: Vec<String> = guard_input(&change_request, "Managing Agent", "change request");
+        if !injection_matches.is_empty() && get_user_response("continue anyway? [y/N]").to_lowercase() != "y" {
+            continue;
+        }
+
+        match manage_agent.revise_project(change_request).await {
+            Ok(revision) => println!("revision {} finished. succeeded: {}", revision.number, revision.succeeded),
+            Err(e) => println!("change request stopped: {}", e),
+        }
+    }
+}
+
+//runs one tool-using agent against an existing project, its tools follow tool_permissions.json
+async fn run_agent(run_dir: &Path, position: &str, objective: &str) {
+    set_run_dir(run_dir);
+    start_event_log(run_dir).expect("failed to start run event log");
+    let mut factsheet: FactSheet = load_factsheet();
+
+    let is_offline: bool = is_offline_build(&active_code_template(&factsheet));
+    let tools: ToolSet = agent_toolset(position, &load_tool_permissions(), &project_dir(), is_offline);
+    let mut agent: AgentReAct = AgentReAct::new(position, objective, tools, ReActLimits::default());
+
+    match agent.execute(&mut factsheet).await {
+        Ok(()) => println!("{} finished: {}", position, agent.result().cloned().unwrap_or_default()),
+        Err(e) => println!("{} stopped: {}", position, e),
+    }
+    save_factsheet(&factsheet);
+}
+
+//vendors and prebuilds one template, or every template when no name is given
+fn warm_up(template_name: Option<&String>) {
+    let registry: TemplateRegistry = TemplateRegistry::load_default();
+
+    let templates: Vec<&CodeTemplate> = match template_name {
+        Some(name) => vec![registry
+            .get(name)
+            .unwrap_or_else(|| panic!("no code template named {}", name))],
+        None => registry.all().iter().collect(),
+    };
+
+    for template in templates {
+        println!("warming up {}...", template.manifest.name);
+        match warm_up_template(template) {
+            Ok(()) => println!("{} is ready for offline builds", template.manifest.name),
+            Err(e) => println!("failed to warm up {}: {}", template.manifest.name, e),
+        }
+    }
+}
+
+//eval [--provider openai|mock] [--cases <dir>] [--prompts <dir>|builtin]...
+//every --prompts is run against the same cases and compared in one report
+async fn evaluate_prompts(args: &[String]) {
+    let mut provider_name: String = "openai".to_string();
+    let mut cases_dir: PathBuf = PathBuf::from(EVALS_PATH);
+    let mut prompt_sets: Vec<String> = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value: String = args
+            .next()
+            .unwrap_or_else(|| panic!("missing value for {}", arg))
+            .to_string();
+        match arg.as_str() {
+            "--provider" => provider_name = value,
+            "--cases" => cases_dir = PathBuf::from(value),
+            "--prompts" => prompt_sets.push(value),
+            _ => panic!("unknown eval option {}", arg),
+        }
+    }
+
+    let provider: Box<dyn EvalProvider> =
+        provider_by_name(&provider_name).unwrap_or_else(|| panic!("no eval provider named {}", provider_name));
+    let cases: Vec<EvalCase> = load_cases(&cases_dir).expect("failed to load eval cases");
+
+    let mut runs: Vec<EvalRun> = vec![];
+    if prompt_sets.is_empty() {
+        runs.push(run_evals(&cases, "default", &PromptRegistry::load_default(), provider.as_ref()).await);
+    }
+    for prompt_set in &prompt_sets {
+        let registry: PromptRegistry = match prompt_set.as_str() {
+            "builtin" => PromptRegistry::default(),
+            dir => PromptRegistry::load(Path::new(dir)).expect("failed to load prompt templates"),
+        };
+        runs.push(run_evals(&cases, prompt_set, &registry, provider.as_ref()).await);
+    }
+
+    for run in &runs {
+        let summary = run.summary();
+        println!("{}: {} of {} cases passed", run.prompts, summary.passed, summary.cases);
+    }
+
+    let timestamp: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let report_path: PathBuf = write_eval_report(&workspaces_root().join(format!("eval_{}", timestamp)), &runs)
+        .expect("failed to write eval report");
+    println!("eval report written to {}", report_path.display());
+}
+
+fn print_llm_stats() {
+    if is_cache_enabled() {
+        let (hits, misses) = cache_stats();
+        println!("LLM cache: {} hits, {} misses", hits, misses);
+    }
+
+    let queue_metrics: QueueMetrics = RATE_LIMITER.metrics();
+    println!(
+        "LLM queue: {} requests, {:.1}s waiting in total, {:.1}s at most",
+        queue_metrics.requests,
+        queue_metrics.total_wait.as_secs_f32(),
+        queue_metrics.max_wait.as_secs_f32()
+    );
+}
 
 #[tokio::main]
 async fn main() {
+    let _telemetry = init_tracing();
+    handle_ctrl_c();
+    let mut args: Vec<String> = env::args().collect();
+
+    //--no-cache can go anywhere, everything else is positional
+    if args.iter().any(|arg| arg == "--no-cache") {
+        disable_cache();
+        args.retain(|arg| arg != "--no-cache");
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("revise") {
+        let run_dir: &str = args
+            .get(2)
+            .expect("usage: auto_gippity revise <run directory>");
+        revise_project(Path::new(run_dir)).await;
+        print_llm_stats();
+        return;
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("agent") {
+        let usage: &str = "usage: auto_gippity agent <run directory> <position> <objective>";
+        let run_dir: &str = args.get(2).expect(usage);
+        let position: &str = args.get(3).expect(usage);
+        let objective: String = args[4..].join(" ");
+        if objective.is_empty() {
+            panic!("{}", usage);
+        }
+        run_agent(Path::new(run_dir), position, &objective).await;
+        print_llm_stats();
+        return;
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("report") {
+        let run_dir: &str = args
+            .get(2)
+            .expect("usage: auto_gippity report <run directory>");
+        set_run_dir(Path::new(run_dir));
+
+        //a run that stopped before saving its fact sheet is reported from its events alone
+        let factsheet: FactSheet = match Path::new(run_dir).join(FACTSHEET_FILE).exists() {
+            true => load_factsheet(),
+            false => FactSheet::new("unknown, the run stopped before saving its fact sheet".to_string()),
+        };
+        let report_path: PathBuf = write_run_report(Path::new(run_dir), &factsheet)
+            .expect("failed to write run report");
+        println!("run report written to {}", report_path.display());
+        return;
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("eval") {
+        evaluate_prompts(&args[2..]).await;
+        print_llm_stats();
+        return;
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("warm-up") {
+        warm_up(args.get(2));
+        return;
+    }
+
+    let run_dir: PathBuf = create_run_dir();
+    println!("run directory: {}", run_dir.display());
+    start_event_log(&run_dir).expect("failed to start run event log");
+
     let usr_req = get_user_response("what website    are we gonna build?");
 
-    let mut manage_agent: ManagingAgent = ManagingAgent::new(usr_req)
-        .await
-        .expect("Error creating agent");    
+    //the request is pasted straight into the first prompt
+    let injection_matches: Vec<String> = guard_input(&usr_req, "Managing Agent", "user request");
+    if !injection_matches.is_empty() && get_user_response("continue anyway? [y/N]").to_lowercase() != "y" {
+        return;
+    }
+
+    let mut manage_agent: ManagingAgent = match ManagingAgent::new(usr_req).await {
+        Ok(manage_agent) => manage_agent,
+        Err(e) => {
+            println!("Error creating agent: {}", e);
+            return;
+        }
+    };
 
         manage_agent.execute_project().await;
+        print_llm_stats();
 
         // dbg!(manage_agent);
 }
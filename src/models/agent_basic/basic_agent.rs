@@ -1,6 +1,9 @@
+use crate::helpers::event_log::{emit, RunEvent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::general::llm::Message;
 
+use tracing::Span;
+
 #[derive(Debug, PartialEq)]
 pub enum AgentState {
     Discovery,
@@ -15,6 +18,8 @@ pub struct BasicAgent {
     pub position: String,
     pub state: AgentState,
     pub memory: Vec<Message>,
+    //open while the agent is in its current state, so its length is the time spent there
+    pub state_span: Span,
 }
 
 impl BasicTraits for BasicAgent {
@@ -24,10 +29,21 @@ impl BasicTraits for BasicAgent {
             position,
             state: AgentState::Discovery,
             memory: Vec::from([]),
+            state_span: Span::none(),
         }
     }
 
     fn update_state(&mut self, new_state: AgentState) {
+        //replacing the span closes the previous state's one, a finished agent has none
+        self.state_span = match new_state {
+            AgentState::Finished => Span::none(),
+            _ => tracing::info_span!("agent_state", agent = %self.position, state = ?new_state),
+        };
+        emit(RunEvent::StateTransition {
+            agent: self.position.clone(),
+            from: format!("{:?}", self.state),
+            to: format!("{:?}", new_state),
+        });
         self.state = new_state;
     }
 
@@ -47,3 +63,24 @@ impl BasicTraits for BasicAgent {
         &self.memory
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn tests_state_transitions_open_spans() {
+        let subscriber = tracing_subscriber::registry().with(tracing_subscriber::fmt::layer().with_test_writer());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut agent: BasicAgent = BasicAgent::new("build a todo app".to_string(), "Backend Developer".to_string());
+            assert!(agent.state_span.is_none());
+
+            agent.update_state(AgentState::Working);
+            assert_eq!(agent.state_span.metadata().map(|metadata| metadata.name()), Some("agent_state"));
+
+            agent.update_state(AgentState::Finished);
+            assert!(agent.state_span.is_none());
+        });
+    }
+}
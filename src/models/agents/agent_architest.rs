@@ -1,6 +1,11 @@
 use crate::ai_functions::aifunc_architect::{print_project_scope, print_site_urls};
 use crate::helpers::command_line::PrintCommand;
-use crate::helpers::general::{ai_task_request_decoded, check_status_code};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::apis::tool_calling::ToolSet;
+use crate::helpers::agent_tools::{agent_toolset, load_tool_permissions};
+use crate::helpers::general::{ai_task_request_decoded, ai_task_request_with_tools, check_status_code};
+use crate::helpers::scaffold::project_dir;
+use crate::helpers::templates::{CodeTemplate, TemplateRegistry};
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFunctions};
@@ -8,6 +13,7 @@ use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFuncti
 use async_trait::async_trait;
 use reqwest::Client;
 use std::time::Duration;
+use tracing::{instrument, Span};
 
 #[derive(Debug)]
 pub struct AgentSolutionArchitect {
@@ -22,12 +28,13 @@ impl AgentSolutionArchitect {
             position: "Solutions Architect".to_string(),
             state: AgentState::Discovery,
             memory: vec![],
+            state_span: Span::none(),
         };
 
         Self { attributes }
     }
 
-    async fn call_project_scope(&mut self, factsheet: &mut FactSheet) -> ProjectScope {
+    async fn call_project_scope(&mut self, factsheet: &mut FactSheet) -> Result<ProjectScope, Box<dyn std::error::Error>> {
         let msg_context: String = format!("{}", factsheet.project_description);
 
         let ai_response: ProjectScope = ai_task_request_decoded::<ProjectScope>(
@@ -36,28 +43,59 @@ impl AgentSolutionArchitect {
             get_function_string!(print_project_scope),
             print_project_scope,
         )
-        .await;
+        .await?;
+
+        factsheet.set_project_scope(&self.attributes.position, ai_response);
+
+        //the template decides which framework and libraries the backend may use
+        let registry: TemplateRegistry = TemplateRegistry::load_default();
+        let code_template: &CodeTemplate = registry.select(&ai_response);
+        factsheet.set_code_template(&self.attributes.position, code_template.manifest.name.clone());
+
+        let template_msg: String = format!("Selected code template: {}", code_template.manifest.name);
+        PrintCommand::AICall.print_agent_message(self.attributes.position.as_str(), template_msg.as_str());
 
-        factsheet.project_scope = Some(ai_response.clone());
         self.attributes.update_state(AgentState::Finished);
-        return ai_response;
+        Ok(ai_response)
     }
 
     async fn call_determine_external_urls(
         &mut self,
         factsheet: &mut FactSheet,
         msg_context: String,
-    ) {
-        let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
-            msg_context,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        //with external_http granted the model can try candidate urls itself, they are still checked afterwards
+        let tools: ToolSet = agent_toolset(&self.attributes.position, &load_tool_permissions(), &project_dir(), false);
+
+        let llm_response: String = ai_task_request_with_tools(
+            msg_context.clone(),
             &self.attributes.position,
             get_function_string!(print_site_urls),
             print_site_urls,
+            &tools,
         )
-        .await;
+        .await?;
+
+        //a tool loop that ran out of rounds or answered in prose gets one plain request instead
+        let ai_response: Vec<String> = match serde_json::from_str(&llm_response) {
+            Ok(urls) => urls,
+            Err(e) => {
+                let issue_msg: String = format!("Tool answer was not a url list ({}), asking without tools", e);
+                PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), issue_msg.as_str());
+
+                ai_task_request_decoded::<Vec<String>>(
+                    msg_context,
+                    &self.attributes.position,
+                    get_function_string!(print_site_urls),
+                    print_site_urls,
+                )
+                .await?
+            }
+        };
 
-        factsheet.external_urls = Some(ai_response);
-        self.attributes.state = AgentState::UnitTesting;
+        factsheet.set_external_urls(&self.attributes.position, ai_response);
+        self.attributes.update_state(AgentState::UnitTesting);
+        Ok(())
     }
 }
 
@@ -67,6 +105,7 @@ impl SpecialFunctions for AgentSolutionArchitect {
         &self.attributes
     }
 
+    #[instrument(name = "execute", skip_all, fields(agent = %self.attributes.position))]
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
@@ -74,15 +113,15 @@ impl SpecialFunctions for AgentSolutionArchitect {
         while self.attributes.state != AgentState::Finished {
             match self.attributes.state {
                 AgentState::Discovery => {
-                    let project_scope: ProjectScope = self.call_project_scope(factsheet).await;
+                    let project_scope: ProjectScope = self.call_project_scope(factsheet).await?;
 
                     if project_scope.is_external_url_required {
                         self.call_determine_external_urls(
                             factsheet,
                             factsheet.project_description.clone(),
                         )
-                        .await;
-                        self.attributes.state = AgentState::UnitTesting;
+                        .await?;
+                        self.attributes.update_state(AgentState::UnitTesting);
                     }
                 }
 
@@ -94,19 +133,24 @@ impl SpecialFunctions for AgentSolutionArchitect {
                         .build()
                         .unwrap();
 
-                    let urls: &Vec<String> = factsheet
-                        .external_urls
-                        .as_ref()
-                        .expect("No URL object on factsheet");
+                    let urls: Vec<String> = factsheet.external_urls().expect("No URL object on factsheet");
 
-                    for url in urls {
+                    for url in &urls {
                         let endpoint_str: String = format!("Testing URL Endpoint: {}", url);
                         PrintCommand::UnitTest.print_agent_message(
                             self.attributes.position.as_str(),
                             endpoint_str.as_str(),
                         );
 
-                        match check_status_code(&client, url).await {
+                        let status_res = check_status_code(&client, url).await;
+
+                        emit(RunEvent::UrlCheck {
+                            url: url.clone(),
+                            status: status_res.as_ref().ok().copied(),
+                            error: status_res.as_ref().err().map(|e| e.to_string()),
+                        });
+
+                        match status_res {
                             Ok(status_code) => {
                                 if status_code != 200 {
                                     exclude_urls.push(url.clone())
@@ -117,22 +161,19 @@ impl SpecialFunctions for AgentSolutionArchitect {
                     }
 
                     if exclude_urls.len() > 0 {
-                        let new_urls: Vec<String> = factsheet
-                            .external_urls
-                            .as_ref()
-                            .unwrap()
+                        let new_urls: Vec<String> = urls
                             .iter()
                             .filter(|url| !exclude_urls.contains(&url))
                             .cloned()
                             .collect();
-                        factsheet.external_urls = Some(new_urls);
+                        factsheet.set_external_urls(&self.attributes.position, new_urls);
                     }
 
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
                 }
 
                 _ => {
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
                 }
             }
         }
@@ -149,20 +190,16 @@ mod tests {
     async fn tests_solution_architect() {
         let mut agent: AgentSolutionArchitect = AgentSolutionArchitect::new();
 
-        let mut factsheet: FactSheet = FactSheet {
-      project_description: "Build a full stack website with user login and logout that shows latest Forex prices".to_string(),
-      project_scope: None,
-      external_urls: None,
-      backend_code: None,
-      api_endpoint_schema: None,
-    };
+        let mut factsheet: FactSheet = FactSheet::new(
+            "Build a full stack website with user login and logout that shows latest Forex prices".to_string(),
+        );
 
         agent
             .execute(&mut factsheet)
             .await
             .expect("Unable to execute Solutions Architect Agent");
-        assert!(factsheet.project_scope != None);
-        assert!(factsheet.external_urls.is_some());
+        assert!(factsheet.project_scope() != None);
+        assert!(factsheet.external_urls().is_some());
 
         dbg!(factsheet);
     }
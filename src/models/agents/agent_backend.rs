@@ -1,23 +1,58 @@
 use crate::ai_functions::aifunc_backend::{
-    print_backend_webserver_code, print_fixed_code, print_improved_webserver_code,
-    print_rest_api_endpoints,
+    print_backend_integration_tests, print_backend_webserver_code, print_fixed_code,
+    print_fixed_code_edits, print_fixed_code_with_tools, print_improved_webserver_code, print_improved_webserver_edits,
+    print_rest_api_endpoints, print_revised_webserver_code,
 };
 use crate::helpers::general::{
-    check_status_code, read_code_template_contents, save_api_endpoints, save_backend_code,
-    read_exec_main_contents, WEB_SERVER_PROJECT_PATH
+    active_code_template, check_status_code, read_code_template_contents, save_api_endpoints, save_backend_code,
+    save_backend_tests, read_exec_main_contents
 };
-
-use crate::helpers::command_line::{PrintCommand, confirm_safe_code};
-use crate::helpers::general::ai_task_request;
+use crate::helpers::scaffold::{project_dir, scaffold_project};
+
+use crate::helpers::agent_definitions::load_default_agent_definitions;
+use crate::helpers::build_cache::{cargo_command, is_offline_build};
+use crate::helpers::code_edits::apply_code_edits;
+use crate::helpers::command_line::{confirm_safe_code, CodeReview, PrintCommand, ReviewDecision};
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::helpers::safety_scan::{
+    format_scan_findings, has_denied_findings, load_safety_policy, scan_project_files,
+    SafetyPolicy, ScanFinding,
+};
+use crate::apis::tool_calling::ToolSet;
+use crate::helpers::agent_tools::{agent_toolset, load_tool_permissions};
+use crate::helpers::general::{ai_task_request, ai_task_request_with_tools};
+use crate::helpers::telemetry::{traced_output, traced_spawn};
+use crate::helpers::templates::CodeTemplate;
+use crate::helpers::workspace_git::WorkspaceRepo;
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, RouteObject, SpecialFunctions};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_code_reviewer::{
+    format_review_findings, has_blocking_findings, AgentCodeReviewer,
+};
+use crate::models::agents::agent_declarative::AgentDeclarative;
+use crate::models::agents::agent_traits::{FactSheet, ReviewFinding, RouteObject, SpecialFunctions};
 
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode, Url};
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::process::Output;
+use std::time::{Duration, Instant};
 use std::vec;
 use tokio::time;
+use tracing::{instrument, Span};
+
+const FIX_TOOLS: [&str; 5] = ["read_file", "list_files", "search_code", "write_file", "cargo_check"];
+
+//each unit testing stage gets its own attempts, passing one stage does not hide bugs in another
+const MAX_STAGE_FAILURES: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TestStage {
+    SafetyScan,
+    CodeReview,
+    Build,
+    IntegrationTests,
+}
 
 #[derive(Debug)]
 
@@ -25,6 +60,12 @@ pub struct AgentBackendDeveloper {
     attributes: BasicAgent,
     bug_errors: Option<String>,
     bug_count: u8,
+    stage_failures: HashMap<TestStage, u8>,
+    reviewed_code: Option<String>,
+    review_feedback: Option<String>,
+    change_request: Option<String>,
+    workspace_repo: Option<WorkspaceRepo>,
+    last_building_commit: Option<String>,
 }
 
 impl AgentBackendDeveloper {
@@ -34,21 +75,100 @@ impl AgentBackendDeveloper {
             position: "Backend Developer".to_string(),
             state: AgentState::Discovery,
             memory: vec![],
+            state_span: Span::none(),
         };
 
         Self {
             attributes,
             bug_errors: None,
             bug_count: 0,
+            stage_failures: HashMap::new(),
+            reviewed_code: None,
+            review_feedback: None,
+            change_request: None,
+            workspace_repo: None,
+            last_building_commit: None,
         }
     }
 
-    async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) {
-        let code_template_str: String = read_code_template_contents();
+    //starts from the existing code of a finished project instead of the template
+    pub fn new_revision(change_request: String) -> Self {
+        let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        agent.attributes.state = AgentState::Working;
+        agent.change_request = Some(change_request);
+        agent
+    }
+
+    //commits the workspace after each step, versioning problems never stop the agent
+    fn record_step(&mut self, ai_function: &str, outcome: &str) -> Option<String> {
+        if self.workspace_repo.is_none() {
+            match WorkspaceRepo::open(&project_dir()) {
+                Ok(workspace_repo) => self.workspace_repo = Some(workspace_repo),
+                Err(e) => {
+                    let error_msg: String = format!("Unable to version workspace: {}", e);
+                    PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error_msg.as_str());
+                    return None;
+                }
+            }
+        }
+
+        let workspace_repo: &WorkspaceRepo = self.workspace_repo.as_ref()?;
+        match workspace_repo.commit_step(&self.attributes.position, ai_function, outcome) {
+            Ok(commit) => Some(commit),
+            Err(e) => {
+                let error_msg: String = format!("Unable to commit workspace: {}", e);
+                PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error_msg.as_str());
+                None
+            }
+        }
+    }
+
+    //true once the stage has failed too often to keep fixing
+    fn record_bug(&mut self, stage: TestStage, bug_errors: String) -> bool {
+        self.bug_count += 1;
+        self.bug_errors = Some(bug_errors);
+
+        let failures: &mut u8 = self.stage_failures.entry(stage).or_insert(0);
+        *failures += 1;
+        *failures >= MAX_STAGE_FAILURES
+    }
+
+    //the fix loop gives up with the workspace back on the last code that built
+    fn give_up(&self, factsheet: &mut FactSheet, reason: &str) -> Box<dyn std::error::Error> {
+        let issue_msg: String = format!("backend code unit testing: {}", reason);
+        PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), issue_msg.as_str());
+
+        if let (Some(workspace_repo), Some(commit)) = (&self.workspace_repo, &self.last_building_commit) {
+            match workspace_repo.rollback_to(commit, &self.attributes.position) {
+                Ok(_) => {
+                    let rollback_msg: String = format!("Workspace rolled back to last building commit {}", commit);
+                    PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), rollback_msg.as_str());
+                    factsheet.set_backend_code(&self.attributes.position, read_exec_main_contents());
+                }
+                Err(e) => {
+                    let error_msg: String = format!("Unable to roll back workspace: {}", e);
+                    PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error_msg.as_str());
+                }
+            }
+        }
+
+        format!("Error ! {}", reason).into()
+    }
+
+    async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let code_template: CodeTemplate = active_code_template(factsheet);
+        let code_template_str: String = code_template.read_code();
+
+        if !project_dir().join("Cargo.toml").exists() {
+            let scaffold_msg: String = format!("Scaffolding project from template {}", code_template.manifest.name);
+            PrintCommand::AICall.print_agent_message(self.attributes.position.as_str(), scaffold_msg.as_str());
+
+            scaffold_project(&project_dir(), &code_template).expect("Failed to scaffold project");
+        }
 
         let msg_context: String = format!(
-            "CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n",
-            code_template_str, factsheet.project_description
+            "CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n {}",
+            code_template_str, factsheet.project_description, code_template.libraries_prompt()
         );
 
         let ai_response: String = ai_task_request(
@@ -57,52 +177,218 @@ impl AgentBackendDeveloper {
             get_function_string!(print_backend_webserver_code),
             print_backend_webserver_code,
         )
-        .await;
+        .await?;
 
         save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        factsheet.set_backend_code(&self.attributes.position, ai_response);
+        self.record_step(get_function_string!(print_backend_webserver_code), "initial code");
+        Ok(())
     }
 
-    async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) {
+    //applies the model's search/replace blocks to the current code, None if they do not apply
+    fn apply_ai_edits(&self, factsheet: &FactSheet, ai_response: &str) -> Option<String> {
+        match apply_code_edits(&current_backend_code(factsheet), ai_response) {
+            Ok(edited_code) => Some(edited_code),
+            Err(e) => {
+                let error_msg: String = format!("Unable to apply code edits ({}), regenerating the full file", e);
+                PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error_msg.as_str());
+                None
+            }
+        }
+    }
+
+    async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let libraries_str: String = active_code_template(factsheet).libraries_prompt();
+
         let msg_context: String = format!(
-            "CODE TEMPLATE: {:?} \n PROJECT_DESCRIPTION: {:?} \n",
-            factsheet.backend_code, factsheet,
+            "EXISTING_CODE: {} \n PROJECT_DESCRIPTION: {} \n {}",
+            current_backend_code(factsheet), factsheet.project_description, libraries_str,
         );
 
-        let ai_response: String = ai_task_request(
+        let ai_edits: String = ai_task_request(
             msg_context,
             &self.attributes.position,
-            get_function_string!(print_improved_webserver_code),
-            print_backend_webserver_code,
+            get_function_string!(print_improved_webserver_edits),
+            print_improved_webserver_edits,
         )
-        .await;
+        .await?;
+
+        let (ai_response, ai_function): (String, &str) = match self.apply_ai_edits(factsheet, &ai_edits) {
+            Some(edited_code) => (edited_code, get_function_string!(print_improved_webserver_edits)),
+            None => {
+                let msg_context: String = format!(
+                    "CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n {}",
+                    current_backend_code(factsheet), factsheet.project_description, libraries_str,
+                );
+
+                let ai_response: String = ai_task_request(
+                    msg_context,
+                    &self.attributes.position,
+                    get_function_string!(print_improved_webserver_code),
+                    print_improved_webserver_code,
+                )
+                .await?;
+
+                (ai_response, get_function_string!(print_improved_webserver_code))
+            }
+        };
 
         save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        factsheet.set_backend_code(&self.attributes.position, ai_response);
+        self.record_step(ai_function, "improved code");
+        Ok(())
     }
 
-    async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) {
-        let msg_context: String = read_code_template_contents();
+    async fn call_revised_backend_code(&mut self, factsheet: &mut FactSheet, change_request: String) -> Result<(), Box<dyn std::error::Error>> {
+        self.reviewed_code = factsheet.backend_code();
+
+        let libraries_str: String = active_code_template(factsheet).libraries_prompt();
 
         let msg_context: String = format!(
-            "BROKEN CODE: {:?} \n ERROR_BUGS: {:?} \n 
-    THIS FUNCTION ONLY OUTPUTS THE CODE . JUST OUTPUT THE CODE",
-            factsheet.backend_code, self.bug_errors
+            "EXISTING_CODE: {} \n PROJECT_DESCRIPTION: {} \n CHANGE_REQUEST: {} \n {}",
+            current_backend_code(factsheet), factsheet.project_description, change_request, libraries_str
         );
 
         let ai_response: String = ai_task_request(
             msg_context,
             &self.attributes.position,
-            get_function_string!(print_fixed_code),
-            print_fixed_code,
+            get_function_string!(print_revised_webserver_code),
+            print_revised_webserver_code,
+        )
+        .await?;
+
+        save_backend_code(&ai_response);
+        factsheet.set_backend_code(&self.attributes.position, ai_response);
+
+        let outcome: String = format!("revised for change request: {}", change_request);
+        self.record_step(get_function_string!(print_revised_webserver_code), outcome.as_str());
+        Ok(())
+    }
+
+    //lets the model read, write and cargo check the project until it builds, None when it changed nothing usable
+    async fn call_fix_code_with_tools(
+        &mut self,
+        factsheet: &FactSheet,
+        error_bugs: &Option<String>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let code_template: CodeTemplate = active_code_template(factsheet);
+        let mut tools: ToolSet = agent_toolset(
+            &self.attributes.position,
+            &load_tool_permissions(),
+            &project_dir(),
+            is_offline_build(&code_template),
+        );
+        tools.retain(&FIX_TOOLS);
+        if tools.get("write_file").is_none() || tools.get("cargo_check").is_none() {
+            return Ok(None);
+        }
+
+        let snapshot: Option<String> = self.record_step(get_function_string!(print_fixed_code_with_tools), "before tool fix");
+        let code_before: String = read_exec_main_contents();
+        let msg_context: String = format!(
+            "ERROR_BUGS: {} \n {}",
+            error_bugs.as_deref().unwrap_or_default(),
+            code_template.libraries_prompt()
+        );
+
+        let summary_res = ai_task_request_with_tools(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_fixed_code_with_tools),
+            print_fixed_code_with_tools,
+            &tools,
         )
         .await;
 
+        //a cancelled tool loop leaves no half written files behind
+        let summary: String = match summary_res {
+            Ok(summary) => summary,
+            Err(e) => {
+                self.restore_snapshot(snapshot.as_deref(), &code_before);
+                return Err(e);
+            }
+        };
+
+        let fixed_code: String = read_exec_main_contents();
+        if fixed_code == code_before || syn::parse_file(&fixed_code).is_err() {
+            self.restore_snapshot(snapshot.as_deref(), &code_before);
+            return Ok(None);
+        }
+
+        let fixed_msg: String = format!("Fixed with tools: {}", summary.trim());
+        PrintCommand::AICall.print_agent_message(self.attributes.position.as_str(), fixed_msg.as_str());
+        Ok(Some(fixed_code))
+    }
+
+    //undoes every file the tools wrote, only main.rs can be put back without a workspace repository
+    fn restore_snapshot(&self, snapshot: Option<&str>, code_before: &String) {
+        if let (Some(workspace_repo), Some(commit)) = (&self.workspace_repo, snapshot) {
+            match workspace_repo.rollback_to(commit, &self.attributes.position) {
+                Ok(_) => return,
+                Err(e) => {
+                    let error_msg: String = format!("Unable to roll back tool changes: {}", e);
+                    PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error_msg.as_str());
+                }
+            }
+        }
+        save_backend_code(code_before);
+    }
+
+    async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let error_bugs: Option<String> = match self.review_feedback.take() {
+            Some(feedback) => Some(format!("HUMAN REVIEWER FEEDBACK: {}", feedback)),
+            None => self.bug_errors.clone(),
+        };
+
+        //the edit blocks below are the fallback when the tools get nowhere
+        if let Some(fixed_code) = self.call_fix_code_with_tools(factsheet, &error_bugs).await? {
+            factsheet.set_backend_code(&self.attributes.position, fixed_code);
+
+            let outcome: String = format!("bug fix attempt {}", self.bug_count);
+            self.record_step(get_function_string!(print_fixed_code_with_tools), outcome.as_str());
+            return Ok(());
+        }
+
+        let msg_context: String = fix_code_context(&current_backend_code(factsheet), &error_bugs);
+
+        let ai_edits: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_fixed_code_edits),
+            print_fixed_code_edits,
+        )
+        .await?;
+
+        let (ai_response, ai_function): (String, &str) = match self.apply_ai_edits(factsheet, &ai_edits) {
+            Some(edited_code) => (edited_code, get_function_string!(print_fixed_code_edits)),
+            None => {
+                let msg_context: String = format!(
+                    "{} \n 
+    THIS FUNCTION ONLY OUTPUTS THE CODE . JUST OUTPUT THE CODE",
+                    fix_code_context(&current_backend_code(factsheet), &error_bugs)
+                );
+
+                let ai_response: String = ai_task_request(
+                    msg_context,
+                    &self.attributes.position,
+                    get_function_string!(print_fixed_code),
+                    print_fixed_code,
+                )
+                .await?;
+
+                (ai_response, get_function_string!(print_fixed_code))
+            }
+        };
+
         save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        factsheet.set_backend_code(&self.attributes.position, ai_response);
+
+        let outcome: String = format!("bug fix attempt {}", self.bug_count);
+        self.record_step(ai_function, outcome.as_str());
+        Ok(())
     }
 
-    async fn call_extract_rest_api_endpoints(&self) -> String{
+    async fn call_extract_rest_api_endpoints(&self) -> Result<String, Box<dyn std::error::Error>> {
         let backend_code: String = read_exec_main_contents();
 
         let msg_context: String = format!("CODE INPUT {}",backend_code);
@@ -113,11 +399,61 @@ impl AgentBackendDeveloper {
             get_function_string!(print_rest_api_endpoints),
             print_rest_api_endpoints,
         )
-        .await;
+        .await?;
 
-        ai_response 
+        Ok(ai_response)
     }
 
+    async fn call_integration_tests(&self, factsheet: &FactSheet, api_endpoint_str: &String) -> Result<String, Box<dyn std::error::Error>> {
+        let backend_code: String = read_exec_main_contents();
+        let libraries_str: String = active_code_template(factsheet).libraries_prompt();
+
+        let msg_context: String = format!(
+            "CODE INPUT: {} \n API_ENDPOINTS: {} \n {}",
+            backend_code, api_endpoint_str, libraries_str
+        );
+
+        let ai_response: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_backend_integration_tests),
+            print_backend_integration_tests,
+        )
+        .await?;
+
+        save_backend_tests(&ai_response);
+        Ok(ai_response)
+    }
+
+}
+
+//the code goes into prompts as-is so edit blocks copied from it match the file
+fn current_backend_code(factsheet: &FactSheet) -> String {
+    match factsheet.backend_code() {
+        Some(backend_code) => backend_code,
+        None => read_exec_main_contents(),
+    }
+}
+
+fn fix_code_context(broken_code: &str, error_bugs: &Option<String>) -> String {
+    format!(
+        "BROKEN CODE: {} \n ERROR_BUGS: {} \n",
+        broken_code,
+        error_bugs.as_deref().unwrap_or_default()
+    )
+}
+
+fn emit_build_event(command: &str, output: &Output, started: Instant) {
+    emit(RunEvent::Build {
+        command: command.to_string(),
+        succeeded: output.status.success(),
+        output: format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
 }
 
 #[async_trait]
@@ -126,6 +462,7 @@ impl SpecialFunctions for AgentBackendDeveloper {
         &self.attributes
     }
 
+    #[instrument(name = "execute", skip_all, fields(agent = %self.attributes.position))]
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
@@ -134,44 +471,136 @@ impl SpecialFunctions for AgentBackendDeveloper {
             
             match  &self.attributes.state {
                 AgentState::Discovery => {
-                    self.call_initial_backend_code(factsheet).await;
-                    self.attributes.state = AgentState::Working;
+                    self.call_initial_backend_code(factsheet).await?;
+                    self.attributes.update_state(AgentState::Working);
                     continue;
                 }
                 AgentState::Working => {
-                    if self.bug_count == 0 {
-                        self.call_improved_backend_code(factsheet).await;
-                        self.attributes.state = AgentState::UnitTesting;
+                    if let Some(change_request) = self.change_request.take() {
+                        self.call_revised_backend_code(factsheet, change_request).await?;
+                        self.attributes.update_state(AgentState::UnitTesting);
+                        continue;
+                    }
+
+                    if self.bug_count == 0 && self.review_feedback.is_none() {
+                        self.call_improved_backend_code(factsheet).await?;
+                        self.attributes.update_state(AgentState::UnitTesting);
                         continue;
                     } else {
-                        self.call_fix_code_bugs(factsheet).await;
-                        self.attributes.state = AgentState::UnitTesting;
+                        self.call_fix_code_bugs(factsheet).await?;
+                        self.attributes.update_state(AgentState::UnitTesting);
                         continue;
                     }
                 }
                 AgentState::UnitTesting => {
 
+                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
+                    "backend code unit testing: Scanning code for unsafe operations");
+
+                    let mut safety_policy: SafetyPolicy = load_safety_policy();
+                    safety_policy
+                        .allowed_dependencies
+                        .extend(active_code_template(factsheet).allowed_libraries());
+                    let backend_code: String = factsheet.backend_code().unwrap_or_default();
+                    let external_urls: Vec<String> = factsheet.external_urls().unwrap_or_default();
+
+                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(),
+                    "backend code unit testing: Writing integration tests...");
+
+                    //the tests are written before the scan so cargo test never runs unscanned code
+                    let api_endpoint_str: String = self.call_extract_rest_api_endpoints().await?;
+                    let test_code: String = self.call_integration_tests(factsheet, &api_endpoint_str).await?;
+
+                    //every file the agents wrote is scanned from disk, nothing is built or run before it passes
+                    let scan_findings: Vec<ScanFinding> = scan_project_files(&project_dir(), &external_urls, &safety_policy);
+
+                    if has_denied_findings(&scan_findings) {
+                        if self.record_bug(TestStage::SafetyScan, format_scan_findings(&scan_findings)) {
+                            return Err(self.give_up(factsheet, "Code keeps breaking the safety policy"));
+                        }
+
+                        self.attributes.update_state(AgentState::Working);
+                        continue;
+                    }
+
+                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
+                    "backend code unit testing: Reviewing code");
+
+                    let mut code_reviewer: AgentCodeReviewer = AgentCodeReviewer::new();
+                    code_reviewer.execute(factsheet).await?;
+
+                    //declarative reviewers append their findings to the code reviewer's
+                    for definition in load_default_agent_definitions() {
+                        if definition.is_code_reviewer() {
+                            AgentDeclarative::new(definition).execute(factsheet).await?;
+                        }
+                    }
+
+                    let mut findings: Vec<ReviewFinding> = factsheet.code_review().unwrap_or_default();
+
+                    if has_blocking_findings(&findings) {
+                        if self.record_bug(TestStage::CodeReview, format_review_findings(&findings)) {
+                            return Err(self.give_up(factsheet, "Code review keeps failing"));
+                        }
+
+                        self.attributes.update_state(AgentState::Working);
+                        continue;
+                    }
+
                     PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
                     "backend code unit testing: Requesting user input");
 
-                    let is_safe_code = confirm_safe_code();
+                    findings.extend(scan_findings.iter().map(|finding| finding.to_review_finding()));
+
+                    let previous_code: String = match &self.reviewed_code {
+                        Some(reviewed_code) => reviewed_code.clone(),
+                        None => read_code_template_contents(factsheet),
+                    };
 
-                    if !is_safe_code {
-                        panic!("Better go work on AI alignment instead......    ")
+                    let review_decision: ReviewDecision = confirm_safe_code(&CodeReview {
+                        code: &backend_code,
+                        previous_code: &previous_code,
+                        test_code: &test_code,
+                        external_urls: &external_urls,
+                        findings: &findings,
+                    });
+
+                    match review_decision {
+                        ReviewDecision::Approve => {
+                            self.reviewed_code = Some(backend_code.clone());
+                        }
+                        ReviewDecision::Reject => {
+                            return Err("Better go work on AI alignment instead......    ".into());
+                        }
+                        ReviewDecision::Edited(edited_code) => {
+                            //edited code goes through the scan and review again
+                            self.reviewed_code = Some(backend_code.clone());
+                            save_backend_code(&edited_code);
+                            factsheet.set_backend_code(&self.attributes.position, edited_code);
+                            self.record_step("confirm_safe_code", "edited by human reviewer");
+                            continue;
+                        }
+                        ReviewDecision::Feedback(feedback) => {
+                            self.reviewed_code = Some(backend_code.clone());
+                            self.review_feedback = Some(feedback);
+                            self.attributes.update_state(AgentState::Working);
+                            continue;
+                        }
                     }
 
                     PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
                     "backend code unit testing: Building project");
 
-                    let build_backend_server: std::process::Output = Command::new("cargo")
-                        .arg("build").current_dir(WEB_SERVER_PROJECT_PATH)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .output()
+                    let is_offline: bool = is_offline_build(&active_code_template(factsheet));
+
+                    let build_started: Instant = Instant::now();
+                    let build_backend_server: std::process::Output = traced_output(&mut cargo_command("build", &project_dir(), is_offline))
                         .expect("Failed to build backend application");
 
+                    emit_build_event("cargo build", &build_backend_server, build_started);
+
                     if build_backend_server.status.success() {
-                        self.bug_count = 0;
+                        self.last_building_commit = self.record_step("cargo build", "build succeeded");
 
                         PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
                         "backend code unit testing: Test server build successful...");
@@ -179,22 +608,15 @@ impl SpecialFunctions for AgentBackendDeveloper {
                         let error_array = build_backend_server.stderr;
                         let error_str = String::from_utf8(error_array).unwrap();
 
-                        self.bug_count += 1;
-                        self.bug_errors = Some(error_str);
-
-                        if self.bug_count > 2 {
-                            PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), 
-                            "backend code unit testing: Too may bugs found in code");
-
-                            panic!("Error ! Too may bugs")
+                        self.record_step("cargo build", "build failed");
+                        if self.record_bug(TestStage::Build, error_str) {
+                            return Err(self.give_up(factsheet, "Too many bugs found in code"));
                         }
 
-                        self.attributes.state = AgentState::Working;
+                        self.attributes.update_state(AgentState::Working);
                         continue;
                     }
 
-                    let api_endpoint_str = self.call_extract_rest_api_endpoints().await;
-
                     let api_endpoints: Vec<RouteObject> = serde_json::from_str(&api_endpoint_str.as_str())
                         .expect("Failed to decode API Endpoints");
 
@@ -204,16 +626,12 @@ impl SpecialFunctions for AgentBackendDeveloper {
                             route_object.method == "get" && route_object.is_route_dynamic == "false"
                         }).cloned().collect();
 
-                        factsheet.api_endpoint_schema = Some(check_endpoints.clone());
+                        factsheet.set_api_endpoint_schema(&self.attributes.position, check_endpoints.clone());
 
                         PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), 
                         "backend code unit testing: Starting web server...");
 
-                        let mut run_backend_server: std::process::Child = Command::new("cargo")
-                        .arg("run").current_dir(WEB_SERVER_PROJECT_PATH)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn()
+                        let mut run_backend_server: std::process::Child = traced_spawn(&mut cargo_command("run", &project_dir(), is_offline))
                         .expect("Failed to run backend application");
 
 
@@ -237,7 +655,15 @@ impl SpecialFunctions for AgentBackendDeveloper {
 
                             let url = format!("http://localhost:8080{}", endpoint.route);
 
-                            match check_status_code(&client, &url).await {
+                            let status_res = check_status_code(&client, &url).await;
+
+                            emit(RunEvent::EndpointResult {
+                                route: endpoint.route.clone(),
+                                status: status_res.as_ref().ok().copied(),
+                                error: status_res.as_ref().err().map(|e| e.to_string()),
+                            });
+
+                            match status_res {
                                 Ok(status_code) => {
                                     if status_code != 200 {
                                         let error_msg = format!("WARNING ! Failed to call backend url endpoint {}",endpoint.route);
@@ -268,16 +694,42 @@ impl SpecialFunctions for AgentBackendDeveloper {
 
 
                     save_api_endpoints(&api_endpoint_str);
-                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(),
-                    "Backend testing is complete..."
-                    );
 
                     run_backend_server
                                         .kill()
                                         .expect("Failed to kill backend server on completion");
 
+                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(),
+                    "backend code unit testing: Running integration tests...");
+
+                    let test_started: Instant = Instant::now();
+                    let test_backend_server: std::process::Output = traced_output(&mut cargo_command("test", &project_dir(), is_offline))
+                        .expect("Failed to run backend integration tests");
+
+                    emit_build_event("cargo test", &test_backend_server, test_started);
+
+                    if !test_backend_server.status.success() {
+                        let error_str = format!(
+                            "{}\n{}",
+                            String::from_utf8_lossy(&test_backend_server.stdout),
+                            String::from_utf8_lossy(&test_backend_server.stderr)
+                        );
+
+                        if self.record_bug(TestStage::IntegrationTests, error_str) {
+                            return Err(self.give_up(factsheet, "Too many failing integration tests"));
+                        }
+
+                        self.attributes.update_state(AgentState::Working);
+                        continue;
+                    }
+
+                    self.bug_count = 0;
+                    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(),
+                    "Backend testing is complete..."
+                    );
+
 
-                    self.attributes.state = AgentState::Finished;
+                    self.attributes.update_state(AgentState::Finished);
 
                 }
                 _ => {}
@@ -290,6 +742,46 @@ impl SpecialFunctions for AgentBackendDeveloper {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::scaffold::create_run_dir;
+
+    #[test]
+    fn tests_bug_counts_per_stage() {
+        let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+
+        //a fix that gets past the build still has its own attempts at the integration tests
+        assert!(!agent.record_bug(TestStage::Build, "mismatched types".to_string()));
+        assert!(!agent.record_bug(TestStage::Build, "mismatched types".to_string()));
+        assert!(!agent.record_bug(TestStage::IntegrationTests, "test todos ... FAILED".to_string()));
+        assert!(!agent.record_bug(TestStage::IntegrationTests, "test todos ... FAILED".to_string()));
+
+        assert!(agent.record_bug(TestStage::Build, "cannot find value".to_string()));
+        assert_eq!(agent.bug_count, 5);
+        assert_eq!(agent.bug_errors.as_deref(), Some("cannot find value"));
+    }
+
+    #[test]
+    fn tests_edits_copied_from_fix_prompt_apply() {
+        let agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        let broken_code: &str = "fn main() {\n    let greeting: &str = \"hello\";\n    println!(\"{}\", greetng);\n}\n";
+        let mut factsheet: FactSheet = FactSheet::new("build a website that says hello".to_string());
+        factsheet.set_backend_code("Backend Developer", broken_code.to_string());
+
+        //the model copies the lines to replace out of the prompt it was given
+        let msg_context: String = fix_code_context(&current_backend_code(&factsheet), &Some("cannot find value `greetng`".to_string()));
+        let prompt_code: &str = msg_context
+            .strip_prefix("BROKEN CODE: ")
+            .and_then(|rest| rest.split(" \n ERROR_BUGS: ").next())
+            .unwrap();
+        let copied_line: &str = prompt_code.lines().nth(2).unwrap();
+        let ai_edits: String = format!(
+            "<<<<<<< SEARCH\n{}\n=======\n    println!(\"{{}}\", greeting);\n>>>>>>> REPLACE",
+            copied_line
+        );
+
+        let fixed_code: String = agent.apply_ai_edits(&factsheet, &ai_edits).unwrap();
+        assert!(fixed_code.contains("println!(\"{}\", greeting);"));
+        assert!(msg_context.ends_with("ERROR_BUGS: cannot find value `greetng` \n"));
+    }
 
     #[tokio::test]
     async fn tests_backend_developer() {
@@ -312,6 +804,7 @@ mod tests {
 
         let mut factsheet: FactSheet = serde_json::from_str(factsheet_str).unwrap();
 
+        create_run_dir();
         agent.attributes.state = AgentState::Discovery;
 
         
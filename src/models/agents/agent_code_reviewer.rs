@@ -0,0 +1,174 @@
+use crate::ai_functions::aifunc_reviewer::print_code_review;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::ai_task_request;
+use crate::helpers::scaffold::project_dir;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{
+    FactSheet, FindingSeverity, ReviewFinding, SpecialFunctions,
+};
+
+use async_trait::async_trait;
+use tracing::{instrument, Span};
+
+#[derive(Debug)]
+pub struct AgentCodeReviewer {
+    attributes: BasicAgent,
+}
+
+impl AgentCodeReviewer {
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Reviews generated backend code for security issues, bugs and spec coverage"
+                .to_string(),
+            position: "Code Reviewer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+            state_span: Span::none(),
+        };
+
+        Self { attributes }
+    }
+
+    async fn call_code_review(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let backend_code: String = factsheet.backend_code().ok_or("No backend code on factsheet to review")?;
+
+        let msg_context: String = format!(
+            "BACKEND_CODE: {} \n PROJECT_DESCRIPTION: {} \n PROJECT_DIRECTORY: {} \n EXTERNAL_URLS: {:?} \n API_ENDPOINTS: {:?} \n",
+            backend_code,
+            factsheet.project_description,
+            project_dir().display(),
+            factsheet.external_urls().unwrap_or_default(),
+            factsheet.api_endpoint_schema(),
+        );
+
+        let llm_response: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_code_review),
+            print_code_review,
+        )
+        .await?;
+
+        //a review in the wrong shape stops the run instead of passing the code unreviewed
+        let ai_response: Vec<ReviewFinding> = serde_json::from_str(&llm_response)
+            .map_err(|e| format!("Code review was not a list of findings: {}", e))?;
+
+        factsheet.set_code_review(&self.attributes.position, ai_response);
+        Ok(())
+    }
+}
+
+//high severity findings stop the code from being run until they are fixed
+pub fn has_blocking_findings(findings: &[ReviewFinding]) -> bool {
+    findings
+        .iter()
+        .any(|finding| finding.severity == FindingSeverity::High)
+}
+
+pub fn format_review_findings(findings: &[ReviewFinding]) -> String {
+    findings
+        .iter()
+        .map(|finding| match finding.line {
+            Some(line) => format!(
+                "[{:?}] {} (line {}): {}",
+                finding.severity, finding.category, line, finding.description
+            ),
+            None => format!(
+                "[{:?}] {}: {}",
+                finding.severity, finding.category, finding.description
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentCodeReviewer {
+    fn get_attributes_from_agents(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    #[instrument(name = "execute", skip_all, fields(agent = %self.attributes.position))]
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.attributes.update_state(AgentState::Discovery);
+
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    self.call_code_review(factsheet).await?;
+                    self.attributes.update_state(AgentState::UnitTesting);
+                }
+
+                AgentState::UnitTesting => {
+                    let findings: Vec<ReviewFinding> = factsheet.code_review().ok_or("No code review on factsheet")?;
+
+                    let review_msg: String = format!("Code review found {} issue(s)", findings.len());
+                    PrintCommand::UnitTest
+                        .print_agent_message(self.attributes.position.as_str(), review_msg.as_str());
+
+                    if has_blocking_findings(&findings) {
+                        PrintCommand::Issue.print_agent_message(
+                            self.attributes.position.as_str(),
+                            "High severity issues found. Code will not be run until they are fixed",
+                        );
+                    }
+
+                    self.attributes.update_state(AgentState::Finished);
+                }
+
+                _ => {
+                    self.attributes.update_state(AgentState::Finished);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_decoding_review_findings() {
+        let review_str: &str = r#"
+      [
+        {
+          "severity": "HIGH",
+          "category": "security",
+          "description": "spawns a shell command",
+          "line": 12
+        },
+        {
+          "severity": "low",
+          "category": "correctness",
+          "description": "unused variable",
+          "line": null
+        }
+      ]"#;
+
+        let findings: Vec<ReviewFinding> = serde_json::from_str(review_str).unwrap();
+
+        assert_eq!(findings[0].severity, FindingSeverity::High);
+        assert!(has_blocking_findings(&findings));
+        assert!(!has_blocking_findings(&findings[1..]));
+        assert_eq!(
+            format_review_findings(&findings),
+            "[High] security (line 12): spawns a shell command\n[Low] correctness: unused variable"
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_review_without_code_is_an_error() {
+        let mut factsheet: FactSheet = FactSheet::new("build a todo app".to_string());
+        let mut code_reviewer: AgentCodeReviewer = AgentCodeReviewer::new();
+
+        let res = code_reviewer.execute(&mut factsheet).await;
+        assert_eq!(res.unwrap_err().to_string(), "No backend code on factsheet to review");
+    }
+}
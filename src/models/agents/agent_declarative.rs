@@ -0,0 +1,228 @@
+use crate::helpers::agent_definitions::{AgentDefinition, PROJECT_DESCRIPTION};
+use crate::helpers::agent_tools::{agent_toolset, load_tool_permissions};
+use crate::helpers::build_cache::is_offline_build;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{active_code_template, ai_spec_request, ai_spec_request_with_tools};
+use crate::helpers::scaffold::project_dir;
+use crate::helpers::telemetry::traced_output;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output};
+use tracing::instrument;
+
+//an agent defined in a config file instead of Rust
+#[derive(Debug)]
+pub struct AgentDeclarative {
+    attributes: BasicAgent,
+    definition: AgentDefinition,
+    notes: Option<String>,
+}
+
+impl AgentDeclarative {
+    pub fn new(definition: AgentDefinition) -> Self {
+        let attributes: BasicAgent = BasicAgent::new(definition.objective.clone(), definition.position.clone());
+
+        Self {
+            attributes,
+            definition,
+            notes: None,
+        }
+    }
+
+    //the inputs as labelled JSON, failing when one has not been produced yet
+    fn input_context(&self, factsheet: &FactSheet) -> Result<String, Box<dyn std::error::Error>> {
+        let mut msg_context: String = String::new();
+        for field in &self.definition.inputs {
+            let Some(value) = factsheet_input(factsheet, field) else {
+                return Err(format!("missing input {}", field).into());
+            };
+            let value_str: String = match value {
+                Value::String(text) => text,
+                value => value.to_string(),
+            };
+            msg_context.push_str(&format!("{}: {} \n ", field.to_uppercase(), value_str));
+        }
+
+        if let Some(notes) = &self.notes {
+            msg_context.push_str(&format!("NOTES: {} \n", notes));
+        }
+        Ok(msg_context)
+    }
+
+    async fn call_discovery(&mut self, factsheet: &FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(prompt) = self.definition.prompts.discovery.clone() else {
+            return Ok(());
+        };
+
+        let msg_context: String = self.input_context(factsheet)?;
+        let notes: String = ai_spec_request(
+            msg_context,
+            &self.attributes.position,
+            &self.definition.function_name("discovery"),
+            &self.definition.function_spec("discovery", &prompt),
+        )
+        .await?;
+        self.notes = Some(notes);
+        Ok(())
+    }
+
+    async fn call_working(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+        let msg_context: String = self.input_context(factsheet)?;
+        let operation: String = self.definition.function_name("working");
+        let spec: String = self.definition.function_spec("working", &self.definition.prompts.working);
+
+        let ai_response: String = match self.definition.uses_tools {
+            true => {
+                let position: &str = &self.attributes.position;
+                let is_offline: bool = is_offline_build(&active_code_template(factsheet));
+                let tools = agent_toolset(position, &load_tool_permissions(), &project_dir(), is_offline);
+                ai_spec_request_with_tools(msg_context, position, &operation, &spec, &tools).await?
+            }
+            false => ai_spec_request(msg_context, &self.attributes.position, &operation, &spec).await?,
+        };
+
+        if let Some(output) = &self.definition.output {
+            write_artefact(factsheet, output, &self.attributes.position, &ai_response)?;
+        }
+        Ok(())
+    }
+
+    async fn run_validation(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for validation in &self.definition.validation {
+            let command_str: String = format!("{} {}", validation.program, validation.args.join(" "));
+            PrintCommand::UnitTest
+                .print_agent_message(&self.attributes.position, &format!("Validating: {}", command_str.trim()));
+
+            let output: Output = run_validation_command(&validation.program, &validation.args, &project_dir()).await?;
+            if !output.status.success() {
+                let error_msg: String = format!(
+                    "validation {} failed: {}",
+                    command_str.trim(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                PrintCommand::Issue.print_agent_message(&self.attributes.position, error_msg.as_str());
+                return Err(error_msg.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn run_validation_command(program: &str, args: &[String], dir: &Path) -> Result<Output, Box<dyn std::error::Error>> {
+    let mut command: Command = Command::new(program);
+    command.args(args).current_dir(dir);
+
+    let output: Output = tokio::task::spawn_blocking(move || traced_output(&mut command)).await??;
+    Ok(output)
+}
+
+//inputs are reached by name so definitions need no code
+fn factsheet_input(factsheet: &FactSheet, name: &str) -> Option<Value> {
+    match name {
+        PROJECT_DESCRIPTION => Some(Value::String(factsheet.project_description.clone())),
+        name => factsheet.artefact(name).map(|artefact| artefact.content.clone()),
+    }
+}
+
+//built-in artefacts keep their type, lists are appended to instead of replaced
+fn write_artefact(
+    factsheet: &mut FactSheet,
+    name: &str,
+    producer: &str,
+    ai_response: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let answer: Value =
+        serde_json::from_str(ai_response.trim()).unwrap_or(Value::String(ai_response.trim().to_string()));
+
+    let content: Value = match (factsheet_input(factsheet, name), answer) {
+        (Some(Value::Array(mut existing)), Value::Array(new)) => {
+            existing.extend(new);
+            Value::Array(existing)
+        }
+        (_, answer) => answer,
+    };
+
+    factsheet
+        .put_value(name, producer, content)
+        .map_err(|e| format!("answer does not fit artefact {}: {}", name, e).into())
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentDeclarative {
+    fn get_attributes_from_agents(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    #[instrument(name = "execute", skip_all, fields(agent = %self.attributes.position))]
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    self.call_discovery(factsheet).await?;
+                    self.attributes.update_state(AgentState::Working);
+                }
+
+                AgentState::Working => {
+                    self.call_working(factsheet).await?;
+                    self.attributes.update_state(AgentState::UnitTesting);
+                }
+
+                AgentState::UnitTesting => {
+                    self.run_validation().await?;
+                    self.attributes.update_state(AgentState::Finished);
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_traits::{FindingSeverity, ReviewFinding};
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn tests_artefacts_by_name() {
+        let mut factsheet: FactSheet = FactSheet::new("build a website that tracks todos".to_string());
+        factsheet.set_code_review(
+            "Code Reviewer",
+            vec![ReviewFinding {
+                severity: FindingSeverity::Low,
+                category: "style".to_string(),
+                description: "unused import".to_string(),
+                line: Some(1),
+            }],
+        );
+
+        assert_eq!(factsheet_input(&factsheet, "external_urls"), None);
+        assert!(factsheet_input(&factsheet, PROJECT_DESCRIPTION).is_some());
+        write_artefact(&mut factsheet, "backend_code", "Tester", "fn main() {}").unwrap();
+        assert_eq!(factsheet.backend_code().as_deref(), Some("fn main() {}"));
+
+        let finding: &str = r#"[{"severity": "high", "category": "compliance", "description": "plain text passwords", "line": null}]"#;
+        write_artefact(&mut factsheet, "code_review", "Compliance Checker", finding).unwrap();
+        assert_eq!(factsheet.code_review().unwrap().len(), 2);
+        assert_eq!(factsheet.artefact("code_review").unwrap().version, 2);
+        assert!(write_artefact(&mut factsheet, "project_scope", "Tester", "not a scope").is_err());
+
+        write_artefact(&mut factsheet, "compliance_notes", "Compliance Checker", "no personal data").unwrap();
+        assert_eq!(factsheet.get::<String>("compliance_notes").as_deref(), Some("no personal data"));
+
+        let dir: PathBuf = std::env::temp_dir();
+        assert!(run_validation_command("true", &[], &dir).await.unwrap().status.success());
+        assert!(!run_validation_command("false", &[], &dir).await.unwrap().status.success());
+    }
+}
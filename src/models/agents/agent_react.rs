@@ -0,0 +1,431 @@
+use crate::ai_functions::aifunc_react::print_react_step;
+use crate::apis::call_request::AssistantReply;
+use crate::apis::rate_limiter::estimate_tokens;
+use crate::apis::tool_calling::{ChatModel, OpenAiChat, ToolSet};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::helpers::general::extend_ai_function;
+use crate::helpers::scaffold::run_dir;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::general::llm::{Message, Tool, ToolCall};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::instrument;
+
+//inside the run directory, one jsonl file per agent session
+pub const AGENT_STEPS_DIR: &str = "agent_steps";
+
+//the model calls this to end the loop, it is never executed
+pub const FINISH_TOOL: &str = "finish";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReActLimits {
+    pub max_steps: usize,
+    //estimated over everything sent and received
+    pub max_tokens: u32,
+}
+
+impl Default for ReActLimits {
+    fn default() -> Self {
+        Self {
+            max_steps: 20,
+            max_tokens: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReActAction {
+    pub tool: String,
+    pub arguments: String,
+    pub observation: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReActStep {
+    pub number: usize,
+    pub thought: String,
+    pub actions: Vec<ReActAction>,
+    pub tokens_used: u32,
+}
+
+//a generic agent, the objective and toolset replace a hand-written state machine
+pub struct AgentReAct {
+    attributes: BasicAgent,
+    tools: ToolSet,
+    model: Box<dyn ChatModel>,
+    limits: ReActLimits,
+    steps_dir: Option<PathBuf>,
+    //when the agent was started, so a second session does not continue the first one's step file
+    session: u128,
+    steps: Vec<ReActStep>,
+    tokens_used: u32,
+    result: Option<String>,
+}
+
+impl fmt::Debug for AgentReAct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentReAct")
+            .field("attributes", &self.attributes)
+            .field("tools", &self.tools.definitions().len())
+            .field("limits", &self.limits)
+            .field("steps", &self.steps.len())
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+fn finish_tool() -> Tool {
+    Tool::function(
+        FINISH_TOOL,
+        "Ends the work. Call it once the objective is met, or when it cannot be met.",
+        json!({
+            "type": "object",
+            "properties": {"result": {"type": "string", "description": "the outcome, or why the objective cannot be met"}},
+            "required": ["result"]
+        }),
+    )
+}
+
+impl AgentReAct {
+    pub fn new(position: &str, objective: &str, tools: ToolSet, limits: ReActLimits) -> Self {
+        let attributes: BasicAgent = BasicAgent::new(objective.to_string(), position.to_string());
+        let session: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+
+        Self {
+            attributes,
+            tools,
+            model: Box::new(OpenAiChat),
+            limits,
+            steps_dir: None,
+            session,
+            steps: vec![],
+            tokens_used: 0,
+            result: None,
+        }
+    }
+
+    pub fn result(&self) -> Option<&String> {
+        self.result.as_ref()
+    }
+
+    fn steps_path(&self) -> PathBuf {
+        let steps_dir: PathBuf = self.steps_dir.clone().unwrap_or_else(|| run_dir().join(AGENT_STEPS_DIR));
+        let file_name: String = self.attributes.get_position().to_lowercase().replace(' ', "_");
+        steps_dir.join(format!("{}_{}.jsonl", file_name, self.session))
+    }
+
+    //appended as it happens so an interrupted run keeps its steps
+    fn persist_step(&self, step: &ReActStep) -> std::io::Result<()> {
+        let path: PathBuf = self.steps_path();
+        if let Some(steps_dir) = path.parent() {
+            fs::create_dir_all(steps_dir)?;
+        }
+
+        let mut file: fs::File = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(step).expect("failed to encode agent step"))
+    }
+
+    fn start_conversation(&mut self, factsheet: &FactSheet) {
+        let msg_context: String = format!(
+            "POSITION: {} \n OBJECTIVE: {} \n PROJECT: {}",
+            self.attributes.get_position(),
+            self.attributes.get_objective(),
+            serde_json::to_string(factsheet).expect("failed to encode factsheet")
+        );
+
+        self.attributes.memory = extend_ai_function(print_react_step, &msg_context);
+    }
+
+    //one think, act and observe round, true once the model called finish
+    async fn call_step(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut definitions: Vec<Tool> = self.tools.definitions();
+        definitions.push(finish_tool());
+
+        let messages: Vec<Message> = self.attributes.get_memory().clone();
+        let sent_tokens: u32 = messages.iter().map(message_tokens).sum::<u32>()
+            + estimate_tokens(&serde_json::to_string(&definitions).unwrap_or_default());
+
+        let reply: AssistantReply = self
+            .model
+            .chat(self.attributes.get_position(), messages, &definitions)
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error> { e.to_string().into() })?;
+        self.tokens_used += sent_tokens + message_tokens(&reply.to_message());
+        self.attributes.memory.push(reply.to_message());
+
+        let mut actions: Vec<ReActAction> = vec![];
+        let mut is_finished: bool = false;
+
+        for tool_call in &reply.tool_calls {
+            if tool_call.function.name == FINISH_TOOL {
+                self.result = Some(finish_result(tool_call));
+                is_finished = true;
+                break;
+            }
+
+            let observation: Message = self.tools.execute(self.attributes.get_position(), tool_call).await;
+            actions.push(ReActAction {
+                tool: tool_call.function.name.clone(),
+                arguments: tool_call.function.arguments.clone(),
+                observation: observation.content.clone(),
+            });
+            self.attributes.memory.push(observation);
+        }
+
+        //a thought without an action gets a nudge instead of ending the loop
+        if reply.tool_calls.is_empty() {
+            self.attributes.memory.push(Message {
+                role: "user".to_string(),
+                content: "Continue with a tool call, or call finish when the objective is met.".to_string(),
+                ..Default::default()
+            });
+        }
+
+        let step: ReActStep = ReActStep {
+            number: self.steps.len() + 1,
+            thought: reply.content.trim().to_string(),
+            actions,
+            tokens_used: self.tokens_used,
+        };
+        self.record_step(step);
+
+        Ok(is_finished)
+    }
+
+    fn record_step(&mut self, step: ReActStep) {
+        let step_msg: String = match step.actions.is_empty() {
+            true => format!("step {}: {}", step.number, step.thought),
+            false => format!(
+                "step {}: {} ({})",
+                step.number,
+                step.thought,
+                step.actions.iter().map(|action| action.tool.as_str()).collect::<Vec<&str>>().join(", ")
+            ),
+        };
+        PrintCommand::AICall.print_agent_message(self.attributes.get_position(), step_msg.as_str());
+
+        emit(RunEvent::AgentStep {
+            agent: self.attributes.get_position().clone(),
+            step: step.number,
+            thought: step.thought.clone(),
+            tools: step.actions.iter().map(|action| action.tool.clone()).collect(),
+            tokens_used: step.tokens_used,
+        });
+
+        if let Err(e) = self.persist_step(&step) {
+            let error_msg: String = format!("Unable to save agent step: {}", e);
+            PrintCommand::Issue.print_agent_message(self.attributes.get_position(), error_msg.as_str());
+        }
+        self.steps.push(step);
+    }
+}
+
+//tool calls and their arguments are sent back every step, often more than the text
+fn message_tokens(message: &Message) -> u32 {
+    let tool_call_tokens: u32 = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|tool_call| estimate_tokens(&tool_call.function.name) + estimate_tokens(&tool_call.function.arguments))
+        .sum();
+    estimate_tokens(&message.content) + tool_call_tokens
+}
+
+fn finish_result(tool_call: &ToolCall) -> String {
+    serde_json::from_str::<Value>(&tool_call.function.arguments)
+        .ok()
+        .and_then(|arguments| arguments["result"].as_str().map(|result| result.to_string()))
+        .unwrap_or(tool_call.function.arguments.clone())
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentReAct {
+    fn get_attributes_from_agents(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    #[instrument(name = "execute", skip_all, fields(agent = %self.attributes.position))]
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    self.start_conversation(factsheet);
+                    self.attributes.update_state(AgentState::Working);
+                }
+
+                AgentState::Working => {
+                    if self.call_step().await? {
+                        self.attributes.update_state(AgentState::Finished);
+                        continue;
+                    }
+
+                    let limit_reached: Option<String> = if self.steps.len() >= self.limits.max_steps {
+                        Some(format!("step limit of {} reached", self.limits.max_steps))
+                    } else if self.tokens_used >= self.limits.max_tokens {
+                        Some(format!("token limit of {} reached", self.limits.max_tokens))
+                    } else {
+                        None
+                    };
+
+                    if let Some(reason) = limit_reached {
+                        PrintCommand::Issue.print_agent_message(self.attributes.get_position(), reason.as_str());
+                        self.attributes.update_state(AgentState::Finished);
+                        return Err(reason.into());
+                    }
+                }
+
+                _ => {
+                    self.attributes.update_state(AgentState::Finished);
+                }
+            }
+        }
+
+        if let Some(result) = &self.result {
+            let result_msg: String = format!("finished: {}", result);
+            PrintCommand::AICall.print_agent_message(self.attributes.get_position(), result_msg.as_str());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::tool_calling::{ToolError, ToolHandler};
+    use crate::models::general::llm::FunctionCall;
+    use std::collections::VecDeque;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    struct CountRoutesTool;
+
+    #[async_trait]
+    impl ToolHandler for CountRoutesTool {
+        fn definition(&self) -> Tool {
+            Tool::function("count_routes", "Counts the routes", json!({"type": "object", "properties": {}}))
+        }
+
+        async fn call(&self, _arguments: Value) -> Result<String, ToolError> {
+            Ok("3 routes".to_string())
+        }
+    }
+
+    struct ScriptedModel {
+        replies: Mutex<VecDeque<AssistantReply>>,
+    }
+
+    #[async_trait]
+    impl ChatModel for ScriptedModel {
+        async fn chat(
+            &self,
+            _agent: &str,
+            _messages: Vec<Message>,
+            tools: &[Tool],
+        ) -> Result<AssistantReply, Box<dyn std::error::Error + Send>> {
+            assert_eq!(tools.last().unwrap().function.name, FINISH_TOOL);
+            Ok(self.replies.lock().unwrap().pop_front().unwrap_or_default())
+        }
+    }
+
+    fn reply(thought: &str, tool: &str, arguments: &str) -> AssistantReply {
+        AssistantReply {
+            content: thought.to_string(),
+            tool_calls: vec![ToolCall {
+                id: format!("call_{}", tool),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: tool.to_string(),
+                    arguments: arguments.to_string(),
+                },
+            }],
+        }
+    }
+
+    fn scripted_agent(replies: Vec<AssistantReply>, max_steps: usize, steps_dir: &Path) -> AgentReAct {
+        let mut tools: ToolSet = ToolSet::default();
+        tools.add(Box::new(CountRoutesTool));
+
+        let mut agent: AgentReAct = AgentReAct::new(
+            "Route Counter",
+            "Count the routes of the web server",
+            tools,
+            ReActLimits {
+                max_steps,
+                max_tokens: 100_000,
+            },
+        );
+        agent.model = Box::new(ScriptedModel {
+            replies: Mutex::new(VecDeque::from(replies)),
+        });
+        agent.steps_dir = Some(steps_dir.to_path_buf());
+        agent
+    }
+
+    fn factsheet() -> FactSheet {
+        FactSheet::new("build a website that tracks todos".to_string())
+    }
+
+    #[tokio::test]
+    async fn tests_react_loop_until_finish_or_limit() {
+        let steps_dir: PathBuf = std::env::temp_dir().join(format!("auto_gippity_react_{}", std::process::id()));
+
+        let mut agent: AgentReAct = scripted_agent(
+            vec![
+                reply("I should count the routes", "count_routes", "{}"),
+                reply("The server has 3 routes", FINISH_TOOL, r#"{"result": "3 routes"}"#),
+            ],
+            5,
+            &steps_dir,
+        );
+        agent.execute(&mut factsheet()).await.unwrap();
+
+        assert_eq!(agent.result().map(|result| result.as_str()), Some("3 routes"));
+        assert_eq!(agent.steps[0].actions[0].observation, "3 routes");
+        assert_eq!(agent.attributes.memory[0].role, "system");
+        assert_eq!(agent.attributes.memory.last().unwrap().tool_calls.as_ref().unwrap()[0].function.name, FINISH_TOOL);
+
+        let persisted: Vec<ReActStep> = fs::read_to_string(agent.steps_path())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(persisted, agent.steps);
+
+        //a model that never finishes is stopped at the step limit
+        let mut looping: AgentReAct = scripted_agent(
+            vec![reply("Counting again", "count_routes", "{}"); 3],
+            2,
+            &steps_dir,
+        );
+        looping.session = agent.session + 1;
+        let res = looping.execute(&mut factsheet()).await;
+        assert_eq!(res.unwrap_err().to_string(), "step limit of 2 reached");
+        assert_eq!(looping.steps.len(), 2);
+
+        //the second session has its own step file
+        assert_eq!(fs::read_to_string(agent.steps_path()).unwrap().lines().count(), 2);
+        assert_eq!(fs::read_to_string(looping.steps_path()).unwrap().lines().count(), 2);
+
+        let arguments: String = format!(r#"{{"path": "src/main.rs", "content": "{}"}}"#, "x".repeat(4_000));
+        assert!(message_tokens(&reply("", "write_file", &arguments).to_message()) > 1_000);
+
+        fs::remove_dir_all(&steps_dir).unwrap();
+    }
+}
@@ -1,7 +1,11 @@
 use crate::models::agent_basic::basic_agent::BasicAgent;
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RouteObject {
@@ -17,16 +21,244 @@ pub struct ProjectScope {
     pub is_crud_required: bool,
     pub is_user_login_and_logout: bool,
     pub is_external_url_required: bool,
+    #[serde(default)]
+    pub is_database_required: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingSeverity {
+    #[serde(alias = "Low", alias = "LOW", alias = "info")]
+    Low,
+    #[serde(alias = "Medium", alias = "MEDIUM")]
+    Medium,
+    #[serde(alias = "High", alias = "HIGH", alias = "critical")]
+    High,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReviewFinding {
+    pub severity: FindingSeverity,
+    pub category: String,
+    pub description: String,
+    pub line: Option<u32>,
+}
+
+//names of the artefacts the built-in agents exchange
+pub const PROJECT_SCOPE: &str = "project_scope";
+
+pub const EXTERNAL_URLS: &str = "external_urls";
+
+pub const BACKEND_CODE: &str = "backend_code";
+
+pub const API_ENDPOINT_SCHEMA: &str = "api_endpoint_schema";
+
+pub const CODE_REVIEW: &str = "code_review";
+
+pub const CODE_TEMPLATE: &str = "code_template";
+
+//fields of old fact sheets become artefacts from this producer
+const LEGACY_PRODUCER: &str = "legacy";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Artefact {
+    //starts at 1 and goes up whenever the content changes
+    pub version: u32,
+    pub producer: String,
+    pub created_at: u64,
+    pub content_hash: String,
+    pub content: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(try_from = "FactSheetFile")]
 pub struct FactSheet {
     pub project_description: String,
-    pub project_scope: Option<ProjectScope>,
-    pub external_urls: Option<Vec<String>>,
-    pub backend_code: Option<String>,
-    pub api_endpoint_schema: Option<Vec<RouteObject>>,
+    pub artefacts: BTreeMap<String, Artefact>,
+}
+
+//what is on disk, old fact sheets kept every artefact as a top level field
+#[derive(Deserialize)]
+struct FactSheetFile {
+    project_description: String,
+    #[serde(default)]
+    artefacts: BTreeMap<String, Artefact>,
+    #[serde(flatten)]
+    legacy_fields: BTreeMap<String, serde_json::Value>,
+}
+
+//a hand edited or stale file is rejected here instead of panicking in get later on
+impl TryFrom<FactSheetFile> for FactSheet {
+    type Error = String;
+
+    fn try_from(file: FactSheetFile) -> Result<Self, Self::Error> {
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: file.project_description,
+            artefacts: file.artefacts,
+        };
+
+        for (name, content) in file.legacy_fields {
+            if !content.is_null() && !factsheet.artefacts.contains_key(&name) {
+                factsheet.insert_artefact(&name, LEGACY_PRODUCER, content, 0);
+            }
+        }
+
+        for (name, artefact) in &factsheet.artefacts {
+            check_artefact_type(name, &artefact.content)
+                .map_err(|e| format!("artefact {} has an unexpected type: {}", name, e))?;
+        }
+        Ok(factsheet)
+    }
+}
+
+fn content_hash(content: &serde_json::Value) -> String {
+    Sha256::digest(content.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+//the built-in artefacts have to decode into their type, any other name takes any JSON
+fn check_artefact_type(name: &str, content: &serde_json::Value) -> Result<(), serde_json::Error> {
+    let content: serde_json::Value = content.clone();
+    match name {
+        PROJECT_SCOPE => serde_json::from_value::<ProjectScope>(content).map(|_| ()),
+        EXTERNAL_URLS => serde_json::from_value::<Vec<String>>(content).map(|_| ()),
+        BACKEND_CODE | CODE_TEMPLATE => serde_json::from_value::<String>(content).map(|_| ()),
+        API_ENDPOINT_SCHEMA => serde_json::from_value::<Vec<RouteObject>>(content).map(|_| ()),
+        CODE_REVIEW => serde_json::from_value::<Vec<ReviewFinding>>(content).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+impl FactSheet {
+    pub fn new(project_description: String) -> Self {
+        Self {
+            project_description,
+            artefacts: BTreeMap::new(),
+        }
+    }
+
+    pub fn artefact(&self, name: &str) -> Option<&Artefact> {
+        self.artefacts.get(name)
+    }
+
+    //panics if the stored content is not a T, which put_value and loading rule out for the built-in names
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.artefact(name).map(|artefact| {
+            serde_json::from_value(artefact.content.clone())
+                .unwrap_or_else(|e| panic!("artefact {} has an unexpected type: {}", name, e))
+        })
+    }
+
+    pub fn put<T: Serialize>(&mut self, name: &str, producer: &str, content: &T) {
+        let content: serde_json::Value = serde_json::to_value(content).expect("failed to encode artefact");
+        self.put_value(name, producer, content)
+            .unwrap_or_else(|e| panic!("artefact {} has an unexpected type: {}", name, e));
+    }
+
+    //for content that is only known as JSON, like the answers of declarative agents
+    pub fn put_value(
+        &mut self,
+        name: &str,
+        producer: &str,
+        content: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        check_artefact_type(name, &content)?;
+
+        let created_at: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.insert_artefact(name, producer, content, created_at);
+        Ok(())
+    }
+
+    //unchanged content keeps its version and producer
+    fn insert_artefact(&mut self, name: &str, producer: &str, content: serde_json::Value, created_at: u64) {
+        let content_hash: String = content_hash(&content);
+        let version: u32 = match self.artefacts.get(name) {
+            Some(existing) if existing.content_hash == content_hash => return,
+            Some(existing) => existing.version + 1,
+            None => 1,
+        };
+
+        self.artefacts.insert(
+            name.to_string(),
+            Artefact {
+                version,
+                producer: producer.to_string(),
+                created_at,
+                content_hash,
+                content,
+            },
+        );
+    }
+
+    pub fn project_scope(&self) -> Option<ProjectScope> {
+        self.get(PROJECT_SCOPE)
+    }
+
+    pub fn set_project_scope(&mut self, producer: &str, project_scope: ProjectScope) {
+        self.put(PROJECT_SCOPE, producer, &project_scope);
+    }
+
+    pub fn external_urls(&self) -> Option<Vec<String>> {
+        self.get(EXTERNAL_URLS)
+    }
+
+    pub fn set_external_urls(&mut self, producer: &str, external_urls: Vec<String>) {
+        self.put(EXTERNAL_URLS, producer, &external_urls);
+    }
+
+    pub fn backend_code(&self) -> Option<String> {
+        self.get(BACKEND_CODE)
+    }
+
+    pub fn set_backend_code(&mut self, producer: &str, backend_code: String) {
+        self.put(BACKEND_CODE, producer, &backend_code);
+    }
+
+    pub fn api_endpoint_schema(&self) -> Option<Vec<RouteObject>> {
+        self.get(API_ENDPOINT_SCHEMA)
+    }
+
+    pub fn set_api_endpoint_schema(&mut self, producer: &str, api_endpoint_schema: Vec<RouteObject>) {
+        self.put(API_ENDPOINT_SCHEMA, producer, &api_endpoint_schema);
+    }
+
+    pub fn code_review(&self) -> Option<Vec<ReviewFinding>> {
+        self.get(CODE_REVIEW)
+    }
+
+    pub fn set_code_review(&mut self, producer: &str, code_review: Vec<ReviewFinding>) {
+        self.put(CODE_REVIEW, producer, &code_review);
+    }
+
+    pub fn code_template(&self) -> Option<String> {
+        self.get(CODE_TEMPLATE)
+    }
+
+    pub fn set_code_template(&mut self, producer: &str, code_template: String) {
+        self.put(CODE_TEMPLATE, producer, &code_template);
+    }
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionTarget {
+    Architect,
+    Backend,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Revision {
+    pub number: usize,
+    pub change_request: String,
+    pub routed_to: RevisionTarget,
+    pub succeeded: bool,
+    pub timestamp: u64,
 }
+
 #[async_trait]
 pub trait SpecialFunctions: Debug {
     fn get_attributes_from_agents(&self) -> &BasicAgent;
@@ -36,3 +268,46 @@ pub trait SpecialFunctions: Debug {
         factsheet: &mut FactSheet,
     ) -> Result<(), Box<dyn std::error::Error>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_old_factsheets_load_into_artefacts() {
+        let old_factsheet: &str = r#"{
+            "project_description": "build a todo app",
+            "project_scope": {"is_crud_required": true, "is_user_login_and_logout": false, "is_external_url_required": false},
+            "external_urls": null,
+            "backend_code": "fn main() {}",
+            "api_endpoint_schema": null,
+            "code_review": null,
+            "code_template": "actix_crud"
+        }"#;
+        let mut factsheet: FactSheet = serde_json::from_str(old_factsheet).unwrap();
+
+        assert!(factsheet.project_scope().unwrap().is_crud_required);
+        assert_eq!(factsheet.external_urls(), None);
+        assert_eq!(factsheet.artefact(BACKEND_CODE).unwrap().producer, LEGACY_PRODUCER);
+        assert_eq!(factsheet.artefacts.len(), 3);
+
+        //unchanged content keeps its version, a change bumps it
+        factsheet.set_backend_code("Backend Developer", "fn main() {}".to_string());
+        assert_eq!(factsheet.artefact(BACKEND_CODE).unwrap().version, 1);
+        factsheet.set_backend_code("Backend Developer", "fn main() { run() }".to_string());
+        assert_eq!(factsheet.artefact(BACKEND_CODE).unwrap().version, 2);
+        assert_eq!(factsheet.artefact(BACKEND_CODE).unwrap().producer, "Backend Developer");
+
+        let reloaded: FactSheet = serde_json::from_str(&serde_json::to_string(&factsheet).unwrap()).unwrap();
+        assert_eq!(reloaded, factsheet);
+        assert!(factsheet.put_value(EXTERNAL_URLS, "Tester", serde_json::json!("not a list")).is_err());
+
+        let wrong_legacy_type: &str = r#"{"project_description": "build a todo app", "external_urls": "https://api.example.com"}"#;
+        let error: String = serde_json::from_str::<FactSheet>(wrong_legacy_type).unwrap_err().to_string();
+        assert!(error.starts_with("artefact external_urls has an unexpected type"));
+
+        let mut edited: serde_json::Value = serde_json::to_value(&factsheet).unwrap();
+        edited["artefacts"][PROJECT_SCOPE]["content"] = serde_json::json!(true);
+        assert!(serde_json::from_value::<FactSheet>(edited).is_err());
+    }
+}
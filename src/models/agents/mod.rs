@@ -1,3 +1,6 @@
 pub mod agent_architest;
 pub mod agent_backend;
+pub mod agent_code_reviewer;
+pub mod agent_declarative;
+pub mod agent_react;
 pub mod agent_traits;
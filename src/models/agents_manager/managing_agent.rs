@@ -1,10 +1,19 @@
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agents::agent_traits::{FactSheet, Revision, RevisionTarget, SpecialFunctions};
 
-use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
-use crate::helpers::general::ai_task_request;
+use crate::ai_functions::aifunc_managing::{convert_user_input_to_goal, print_revision_route};
+use crate::helpers::agent_definitions::load_default_agent_definitions;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::event_log::{emit, RunEvent};
+use crate::helpers::general::{ai_task_request, load_revisions, save_factsheet, save_revision};
+use crate::helpers::run_report::write_run_report;
+use crate::helpers::scaffold::run_dir;
 use crate::models::agents::agent_architest::AgentSolutionArchitect;
 use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_declarative::AgentDeclarative;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{instrument, Span};
 
 #[derive(Debug)]
 pub struct ManagingAgent {
@@ -22,6 +31,7 @@ impl ManagingAgent {
             position: position.clone(),
             state: AgentState::Discovery,
             memory: vec![],
+            state_span: Span::none(),
         };
 
         let project_description: String = ai_task_request(
@@ -30,17 +40,11 @@ impl ManagingAgent {
             get_function_string!(print_project_scope),
             convert_user_input_to_goal,
         )
-        .await;
+        .await?;
 
         let agents: Vec<Box<dyn SpecialFunctions>> = vec![];
 
-        let factsheet: FactSheet = FactSheet {
-            project_description,
-            project_scope: None,
-            external_urls: None,
-            backend_code: None,
-            api_endpoint_schema: None,
-        };
+        let factsheet: FactSheet = FactSheet::new(project_description);
 
         Ok(Self {
             attributes,
@@ -49,34 +53,231 @@ impl ManagingAgent {
         })
     }
 
+    //picks up a finished project so the user can ask for changes
+    pub fn from_factsheet(factsheet: FactSheet) -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Manage agents who are revising websites for users".to_string(),
+            position: "project manager".to_string(),
+            state: AgentState::Working,
+            memory: vec![],
+            state_span: Span::none(),
+        };
+
+        Self {
+            attributes,
+            factsheet,
+            agents: vec![],
+        }
+    }
+
     fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
         self.agents.push(agent);
     }
 
+    //declarative agents from the agents directory run after the built-in ones, reviewers run inside the backend
     fn create_agents(&mut self) {
         self.add_agent(Box::new(AgentSolutionArchitect::new()));
         self.add_agent(Box::new(AgentBackendDeveloper::new()));
+
+        for definition in load_default_agent_definitions() {
+            if !definition.is_code_reviewer() {
+                self.add_agent(Box::new(AgentDeclarative::new(definition)));
+            }
+        }
     }
 
+    #[instrument(skip_all)]
     pub async fn execute_project(&mut self) {
         self.create_agents();
 
+        //later agents build on the earlier ones' artefacts, so the first failure ends the run
         for agent in &mut self.agents {
-            let agent_res: Result<(), Box<dyn std::error::Error>> =
-                agent.execute(&mut self.factsheet).await;
+            if let Err(e) = execute_agent(agent.as_mut(), &mut self.factsheet).await {
+                let error_msg: String = format!(
+                    "Run stopped, {} failed: {}",
+                    agent.get_attributes_from_agents().position,
+                    e
+                );
+                PrintCommand::Issue.print_agent_message(&self.attributes.position, error_msg.as_str());
+                break;
+            }
         }
+
+        save_factsheet(&self.factsheet);
+        self.write_report();
+    }
+
+    fn write_report(&self) {
+        match write_run_report(&run_dir(), &self.factsheet) {
+            Ok(path) => {
+                let report_msg: String = format!("Run report written to {}", path.display());
+                PrintCommand::AICall.print_agent_message(&self.attributes.position, report_msg.as_str());
+            }
+            Err(e) => {
+                let error_msg: String = format!("Failed to write run report: {}", e);
+                PrintCommand::Issue.print_agent_message(&self.attributes.position, error_msg.as_str());
+            }
+        }
+    }
+
+    async fn call_revision_route(&self, change_request: &str) -> Result<RevisionTarget, Box<dyn std::error::Error>> {
+        let msg_context: String = format!(
+            "CHANGE_REQUEST: {} \n PROJECT_DESCRIPTION: {} \n",
+            change_request, self.factsheet.project_description
+        );
+
+        let ai_response: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_revision_route),
+            print_revision_route,
+        )
+        .await?;
+
+        Ok(parse_revision_route(&ai_response))
+    }
+
+    #[instrument(skip_all)]
+    pub async fn revise_project(&mut self, change_request: String) -> Result<Revision, Box<dyn std::error::Error>> {
+        let routed_to: RevisionTarget = self.call_revision_route(&change_request).await?;
+
+        let route_msg: String = format!("Routing change request to {:?}", routed_to);
+        PrintCommand::AICall.print_agent_message(&self.attributes.position, route_msg.as_str());
+
+        //scope changes need the architect first, the backend always applies the code change
+        self.agents.clear();
+        if routed_to == RevisionTarget::Architect {
+            self.factsheet.project_description = format!(
+                "{} \n CHANGE REQUEST: {}",
+                self.factsheet.project_description, change_request
+            );
+            self.add_agent(Box::new(AgentSolutionArchitect::new()));
+        }
+        self.add_agent(Box::new(AgentBackendDeveloper::new_revision(change_request.clone())));
+
+        Ok(self.run_revision(change_request, routed_to).await)
+    }
+
+    //a failing agent stops the revision and is recorded rather than ending the process
+    async fn run_revision(&mut self, change_request: String, routed_to: RevisionTarget) -> Revision {
+        let mut succeeded: bool = true;
+        for agent in &mut self.agents {
+            if let Err(e) = execute_agent(agent.as_mut(), &mut self.factsheet).await {
+                let error_msg: String = format!("Revision failed: {}", e);
+                PrintCommand::Issue.print_agent_message(&self.attributes.position, error_msg.as_str());
+                succeeded = false;
+                break;
+            }
+        }
+
+        let timestamp: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        let revision: Revision = Revision {
+            number: load_revisions().len() + 1,
+            change_request,
+            routed_to,
+            succeeded,
+            timestamp,
+        };
+
+        save_revision(&revision);
+        save_factsheet(&self.factsheet);
+        self.write_report();
+        revision
+    }
+}
+
+//runs one agent between started and finished events
+async fn execute_agent(
+    agent: &mut dyn SpecialFunctions,
+    factsheet: &mut FactSheet,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let position: String = agent.get_attributes_from_agents().position.clone();
+    emit(RunEvent::AgentStarted {
+        agent: position.clone(),
+    });
+
+    let agent_res: Result<(), Box<dyn std::error::Error>> = agent.execute(factsheet).await;
+
+    emit(RunEvent::AgentFinished {
+        agent: position,
+        succeeded: agent_res.is_ok(),
+        error: agent_res.as_ref().err().map(|e| e.to_string()),
+    });
+
+    agent_res
+}
+
+pub fn parse_revision_route(ai_response: &str) -> RevisionTarget {
+    let route: String = ai_response
+        .trim()
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '.')
+        .to_lowercase();
+
+    match route.as_str() {
+        "architect" => RevisionTarget::Architect,
+        _ => RevisionTarget::Backend,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::scaffold::create_run_dir;
+    use crate::models::agent_basic::basic_traits::BasicTraits;
+    use async_trait::async_trait;
+
+    #[derive(Debug)]
+    struct AgentGivingUp {
+        attributes: BasicAgent,
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for AgentGivingUp {
+        fn get_attributes_from_agents(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        async fn execute(&mut self, _factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
+            Err("Error ! Too many bugs found in code".into())
+        }
+    }
+
+    #[test]
+    fn tests_parse_revision_route() {
+        assert_eq!(parse_revision_route(" Architect.\n"), RevisionTarget::Architect);
+        assert_eq!(parse_revision_route("\"backend\""), RevisionTarget::Backend);
+        assert_eq!(parse_revision_route("not sure"), RevisionTarget::Backend);
+    }
+
+    #[tokio::test]
+    async fn tests_failed_revision_is_recorded() {
+        create_run_dir();
+
+        let mut managing_agent: ManagingAgent =
+            ManagingAgent::from_factsheet(FactSheet::new("build a website that tracks todos".to_string()));
+        managing_agent.add_agent(Box::new(AgentGivingUp {
+            attributes: BasicAgent::new("fails".to_string(), "Backend Developer".to_string()),
+        }));
+
+        let revision: Revision = managing_agent
+            .run_revision("add a delete route".to_string(), RevisionTarget::Backend)
+            .await;
+
+        assert!(!revision.succeeded);
+        assert_eq!(load_revisions().last(), Some(&revision));
+    }
 
     #[tokio::test]
     async fn tests_managing_agent() {
         let user_request: &str =
             "need a app that tracks my fitness.also need to include timezones from web .";
 
+        create_run_dir();
+
         let mut managing_agent: ManagingAgent = ManagingAgent::new(user_request.to_string())
             .await
             .expect("failed to create managing agent");
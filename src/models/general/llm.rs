@@ -1,10 +1,64 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 
 pub struct Message {
     pub role: String,
     pub content: String,
+    //set on assistant messages that ask for tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    //set on tool messages, the call they answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct FunctionCall {
+    pub name: String,
+    //JSON encoded, as the model wrote it
+    pub arguments: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    //JSON schema of the arguments object
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    pub fn function(name: &str, description: &str, parameters: serde_json::Value) -> Self {
+        Self {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: description.to_string(),
+                parameters,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StreamOptions {
+    pub include_usage: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -12,19 +66,49 @@ pub struct ChatCompletion {
     pub model: String,
     pub messages: Vec<Message>,
     pub temperature: f32,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct APIMessage {
-    pub content: String,
+pub struct APIUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct APIFunctionDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+//a piece of one tool call, the index says which call it belongs to
+#[derive(Debug, Deserialize)]
+pub struct APIToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub function: Option<APIFunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIDelta {
+    pub content: Option<String>,
+    pub tool_calls: Option<Vec<APIToolCallDelta>>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct APIChoice {
-    pub message: APIMessage,
+pub struct APIStreamChoice {
+    pub delta: APIDelta,
 }
 
+//one server-sent event of a streamed completion, the last one only carries usage
 #[derive(Debug, Deserialize)]
-pub struct APIResponse {
-    pub choices: Vec<APIChoice>,
+pub struct APIStreamResponse {
+    pub choices: Vec<APIStreamChoice>,
+    pub usage: Option<APIUsage>,
 }
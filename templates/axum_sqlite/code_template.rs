@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Item {
+    id: i64,
+    name: String,
+    completed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    username: String,
+    password: String,
+}
+
+type Db = Arc<Mutex<Connection>>;
+
+fn open_database() -> Connection {
+    let conn: Connection = Connection::open("database.sqlite").expect("failed to open database");
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS items (id INTEGER PRIMARY KEY, name TEXT NOT NULL, completed INTEGER NOT NULL);
+         CREATE TABLE IF NOT EXISTS users (username TEXT PRIMARY KEY, password TEXT NOT NULL);",
+    )
+    .expect("failed to create tables");
+    conn
+}
+
+async fn list_items(State(db): State<Db>) -> Result<Json<Vec<Item>>, StatusCode> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare("SELECT id, name, completed FROM items")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let items = stmt
+        .query_map([], |row| {
+            Ok(Item {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                completed: row.get(2)?,
+            })
+        })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter_map(Result::ok)
+        .collect();
+    Ok(Json(items))
+}
+
+async fn create_item(State(db): State<Db>, Json(item): Json<Item>) -> StatusCode {
+    let conn = db.lock().unwrap();
+    match conn.execute(
+        "INSERT OR REPLACE INTO items (id, name, completed) VALUES (?1, ?2, ?3)",
+        params![item.id, item.name, item.completed],
+    ) {
+        Ok(_) => StatusCode::CREATED,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn read_item(State(db): State<Db>, Path(id): Path<i64>) -> Result<Json<Item>, StatusCode> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT id, name, completed FROM items WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(Item {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                completed: row.get(2)?,
+            })
+        },
+    )
+    .map(Json)
+    .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+async fn delete_item(State(db): State<Db>, Path(id): Path<i64>) -> StatusCode {
+    let conn = db.lock().unwrap();
+    match conn.execute("DELETE FROM items WHERE id = ?1", params![id]) {
+        Ok(0) => StatusCode::NOT_FOUND,
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn register(State(db): State<Db>, Json(user): Json<User>) -> StatusCode {
+    let conn = db.lock().unwrap();
+    match conn.execute(
+        "INSERT INTO users (username, password) VALUES (?1, ?2)",
+        params![user.username, user.password],
+    ) {
+        Ok(_) => StatusCode::CREATED,
+        Err(_) => StatusCode::CONFLICT,
+    }
+}
+
+async fn login(State(db): State<Db>, Json(user): Json<User>) -> StatusCode {
+    let conn = db.lock().unwrap();
+    let stored: Result<String, _> = conn.query_row(
+        "SELECT password FROM users WHERE username = ?1",
+        params![user.username],
+        |row| row.get(0),
+    );
+    match stored {
+        Ok(password) if password == user.password => StatusCode::OK,
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let db: Db = Arc::new(Mutex::new(open_database()));
+
+    let app = Router::new()
+        .route("/item", get(list_items).post(create_item))
+        .route("/item/:id", get(read_item).delete(delete_item))
+        .route("/register", axum::routing::post(register))
+        .route("/login", axum::routing::post(login))
+        .with_state(db);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
+        .await
+        .expect("failed to bind port 8080");
+    axum::serve(listener, app).await.expect("server failed");
+}
@@ -0,0 +1,49 @@
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use serde_json::Value;
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head><title>Live data</title></head>
+  <body>
+    <h1>Live data</h1>
+    <pre id="data">loading...</pre>
+    <script>
+      fetch("/api/data")
+        .then((res) => res.json())
+        .then((data) => (document.getElementById("data").textContent = JSON.stringify(data, null, 2)));
+    </script>
+  </body>
+</html>"#;
+
+const EXTERNAL_URL: &str = "https://api.coindesk.com/v1/bpi/currentprice.json";
+
+async fn index() -> impl Responder {
+    HttpResponse::Ok().content_type("text/html").body(INDEX_HTML)
+}
+
+async fn proxy_data(client: web::Data<reqwest::Client>) -> impl Responder {
+    let response = match client.get(EXTERNAL_URL).send().await {
+        Ok(response) => response,
+        Err(_) => return HttpResponse::BadGateway().finish(),
+    };
+
+    match response.json::<Value>().await {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(_) => HttpResponse::BadGateway().finish(),
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let client = web::Data::new(reqwest::Client::new());
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(client.clone())
+            .route("/", web::get().to(index))
+            .route("/api/data", web::get().to(proxy_data))
+    })
+    .bind("127.0.0.1:8080")?
+    .run()
+    .await
+}